@@ -0,0 +1,33 @@
+use std::ops::RangeInclusive;
+
+pub mod reuse;
+pub mod stack_distance;
+
+/// Counts values in buckets that double their width, so `0` has its own
+/// bucket and bucket `k` holds the values from `2^(k-1)` to `2^k - 1`.
+#[derive(Default, Clone)]
+pub struct Log2Histogram {
+    counts: Vec<u128>,
+}
+
+impl Log2Histogram {
+    pub fn add(&mut self, value: usize, count: u128) {
+        let bucket = (usize::BITS - value.leading_zeros()) as usize;
+        if self.counts.len() <= bucket {
+            self.counts.resize(bucket + 1, 0);
+        }
+        self.counts[bucket] += count;
+    }
+
+    /// Returns the range of values of every bucket up to the last one that is
+    /// not empty, with its count.
+    pub fn buckets(&self) -> impl Iterator<Item = (RangeInclusive<usize>, u128)> + '_ {
+        self.counts.iter().enumerate().map(|(bucket, &count)| {
+            let range = match bucket {
+                0 => 0..=0,
+                bucket => 1 << (bucket - 1)..=(1 << bucket) - 1,
+            };
+            (range, count)
+        })
+    }
+}
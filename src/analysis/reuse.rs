@@ -0,0 +1,99 @@
+use std::fmt::{Display, Formatter};
+
+use crate::analysis::stack_distance::StackDistance;
+use crate::analysis::Log2Histogram;
+use crate::cache::AccessType::{self, *};
+use crate::cache::ValueType::*;
+use crate::MemoryAddress;
+
+/// Reuses of the accesses of a single type.
+#[derive(Default, Clone)]
+pub struct ReuseHistogram {
+    /// Accesses to blocks that were never used before.
+    pub cold: u128,
+    /// Distinct blocks accessed since the last use of the block.
+    pub distances: Log2Histogram,
+    /// Accesses since the last use of the block.
+    pub times: Log2Histogram,
+}
+
+/// Histograms of the reuse distance and reuse time of the accesses of a
+/// trace, split by instruction reads, data reads and writes.
+///
+/// The last use of a block can be of any type, so a read after a write of
+/// the same block is a reuse.
+#[derive(Clone)]
+pub struct ReuseHistograms {
+    stack_distance: StackDistance,
+    pub instruction_reads: ReuseHistogram,
+    pub data_reads: ReuseHistogram,
+    pub writes: ReuseHistogram,
+}
+
+impl ReuseHistograms {
+    /// Creates the histograms for blocks of `block_size` words.
+    pub fn new(block_size: usize) -> Self {
+        Self {
+            stack_distance: StackDistance::new(block_size),
+            instruction_reads: ReuseHistogram::default(),
+            data_reads: ReuseHistogram::default(),
+            writes: ReuseHistogram::default(),
+        }
+    }
+
+    pub fn access(&mut self, access_type: AccessType, address: MemoryAddress) {
+        let histogram = match access_type {
+            Read(Instruction) => &mut self.instruction_reads,
+            Read(Data) => &mut self.data_reads,
+            Write => &mut self.writes,
+        };
+
+        match self.stack_distance.access(address) {
+            Some(reuse) => {
+                histogram.distances.add(reuse.distance, 1);
+                histogram.times.add(reuse.time, 1);
+            }
+            None => histogram.cold += 1,
+        }
+    }
+}
+
+impl Display for ReuseHistograms {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (name, histogram) in [
+            ("Instruction Reads", &self.instruction_reads),
+            ("Data Reads", &self.data_reads),
+            ("Writes", &self.writes),
+        ] {
+            writeln!(f, "{} Reuses", name)?;
+            writeln!(f, "┌──────────────────┬────────────────┬────────────────┐")?;
+            writeln!(f, "│ Bucket           │ Distance       │ Time           │")?;
+            writeln!(f, "├──────────────────┼────────────────┼────────────────┤")?;
+            writeln!(
+                f,
+                "│ Cold             │ {:<14} │ {:<14} │",
+                histogram.cold, histogram.cold
+            )?;
+
+            let mut distances = histogram.distances.buckets();
+            let mut times = histogram.times.buckets();
+            loop {
+                let (range, distance, time) = match (distances.next(), times.next()) {
+                    (None, None) => break,
+                    (Some((range, distance)), time) => (range, distance, time.map_or(0, |t| t.1)),
+                    (None, Some((range, time))) => (range, 0, time),
+                };
+
+                let label = if range.start() == range.end() {
+                    range.start().to_string()
+                } else {
+                    format!("{}-{}", range.start(), range.end())
+                };
+                writeln!(f, "│ {:<16} │ {:<14} │ {:<14} │", label, distance, time)?;
+            }
+            writeln!(f, "└──────────────────┴────────────────┴────────────────┘")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use crate::analysis::Log2Histogram;
+use crate::{MemoryAddress, WORD_SIZE};
+
+/// How long ago a block was last accessed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Reuse {
+    /// Distinct blocks accessed in between.
+    pub distance: usize,
+    /// Accesses in between.
+    pub time: usize,
+}
+
+/// Computes the LRU stack distance of every access, which is the number of
+/// distinct blocks accessed since the last access to the same block.
+///
+/// A fully associative LRU cache of `n` blocks hits exactly on the accesses
+/// with a distance below `n`, so a single pass gives the miss ratio of every
+/// cache size (Mattson et al.). The distances are counted with a Fenwick tree
+/// that marks the last access to each block, which takes `O(log n)` per
+/// access.
+#[derive(Clone)]
+pub struct StackDistance {
+    block_mask_size: u32,
+    last_uses: HashMap<MemoryAddress, usize>,
+    last_use_marks: Fenwick,
+    accesses: usize,
+    /// Accesses with each distance.
+    distances: Vec<u128>,
+    /// First accesses to a block, which have an infinite distance.
+    cold_accesses: u128,
+}
+
+impl StackDistance {
+    /// Creates an analysis for blocks of `block_size` words.
+    pub fn new(block_size: usize) -> Self {
+        Self {
+            block_mask_size: block_size.ilog2() + WORD_SIZE.ilog2(),
+            last_uses: HashMap::new(),
+            last_use_marks: Fenwick::new(1024),
+            accesses: 0,
+            distances: Vec::new(),
+            cold_accesses: 0,
+        }
+    }
+
+    /// Records an access to `address`, and returns how long ago its block
+    /// was used, if it was used before.
+    pub fn access(&mut self, address: MemoryAddress) -> Option<Reuse> {
+        if self.accesses == self.last_use_marks.len() {
+            self.last_use_marks =
+                Fenwick::with_marks(self.accesses * 2, self.last_uses.values().copied());
+        }
+
+        let block = address >> self.block_mask_size;
+        let reuse = self.last_uses.insert(block, self.accesses).map(|last_use| {
+            let distance = self.last_use_marks.count(last_use + 1, self.accesses);
+            self.last_use_marks.add(last_use, -1);
+
+            if self.distances.len() <= distance {
+                self.distances.resize(distance + 1, 0);
+            }
+            self.distances[distance] += 1;
+
+            Reuse {
+                distance,
+                time: self.accesses - last_use - 1,
+            }
+        });
+        if reuse.is_none() {
+            self.cold_accesses += 1;
+        }
+
+        self.last_use_marks.add(self.accesses, 1);
+        self.accesses += 1;
+        reuse
+    }
+
+    pub fn accesses(&self) -> usize {
+        self.accesses
+    }
+
+    pub fn cold_accesses(&self) -> u128 {
+        self.cold_accesses
+    }
+
+    /// Returns the number of accesses with each distance.
+    pub fn distances(&self) -> &[u128] {
+        &self.distances
+    }
+
+    /// Returns the distances grouped in power of two buckets.
+    pub fn histogram(&self) -> Log2Histogram {
+        let mut histogram = Log2Histogram::default();
+        for (distance, &count) in self.distances.iter().enumerate() {
+            histogram.add(distance, count);
+        }
+        histogram
+    }
+
+    /// Returns the miss ratio of a fully associative LRU cache of `blocks`
+    /// blocks.
+    pub fn miss_ratio(&self, blocks: usize) -> f64 {
+        if self.accesses == 0 {
+            return 0.0;
+        }
+
+        let misses = self.cold_accesses + self.distances.iter().skip(blocks).sum::<u128>();
+        misses as f64 / self.accesses as f64
+    }
+
+    /// Returns the miss ratio for every power of two number of blocks, up to
+    /// the first one where only the cold accesses miss.
+    pub fn miss_ratio_curve(&self) -> Vec<(usize, f64)> {
+        let max_blocks = self.distances.len().max(1).next_power_of_two();
+
+        std::iter::successors(Some(1), |blocks| Some(blocks * 2))
+            .take_while(|&blocks| blocks <= max_blocks)
+            .map(|blocks| (blocks, self.miss_ratio(blocks)))
+            .collect()
+    }
+}
+
+/// Fenwick tree that counts marked positions.
+#[derive(Clone)]
+struct Fenwick {
+    tree: Vec<i64>,
+}
+
+impl Fenwick {
+    fn new(len: usize) -> Self {
+        Self {
+            tree: vec![0; len + 1],
+        }
+    }
+
+    /// Builds a tree of `len` positions with `marks` already marked.
+    fn with_marks(len: usize, marks: impl Iterator<Item = usize>) -> Self {
+        let mut fenwick = Self::new(len);
+        for position in marks {
+            fenwick.tree[position + 1] += 1;
+        }
+        for idx in 1..=len {
+            let parent = idx + (idx & idx.wrapping_neg());
+            if parent <= len {
+                fenwick.tree[parent] += fenwick.tree[idx];
+            }
+        }
+        fenwick
+    }
+
+    fn len(&self) -> usize {
+        self.tree.len() - 1
+    }
+
+    fn add(&mut self, position: usize, value: i64) {
+        let mut idx = position + 1;
+        while idx < self.tree.len() {
+            self.tree[idx] += value;
+            idx += idx & idx.wrapping_neg();
+        }
+    }
+
+    /// Returns the marks before `end`.
+    fn prefix(&self, end: usize) -> i64 {
+        let mut idx = end;
+        let mut sum = 0;
+        while idx > 0 {
+            sum += self.tree[idx];
+            idx -= idx & idx.wrapping_neg();
+        }
+        sum
+    }
+
+    /// Returns the marks from `start` to `end`, not including `end`.
+    fn count(&self, start: usize, end: usize) -> usize {
+        (self.prefix(end) - self.prefix(start)) as usize
+    }
+}
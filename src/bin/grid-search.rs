@@ -3,7 +3,7 @@ use cache_simulator::logger::Logger;
 use cache_simulator::map_strategies::fully_associative::FullyAssociativeFactory;
 use cache_simulator::{cli_parser::ParsedArgs, trace_simulator::TraceSimulator, WORD_SIZE};
 use std::error::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Copy)]
 struct LockedParams {
@@ -205,13 +205,16 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// The best arguments found for a cache size, with their metric value and logs.
+type BestConfiguration = (Option<ParsedArgs>, f64, Option<Logger>);
+
 fn find_best_configuration(
     size: usize,
     locked_params: &LockedParams,
-    file_path: &PathBuf,
+    file_path: &Path,
     metric: &Metric,
     metric_stats: &mut MetricStats,
-) -> Result<(Option<ParsedArgs>, f64, Option<Logger>), Box<dyn Error>> {
+) -> Result<BestConfiguration, Box<dyn Error>> {
     let mut best_args = None;
     let mut best_metric_value = f64::INFINITY;
     let mut best_logs = None;
@@ -269,11 +272,12 @@ fn find_best_configuration(
                         let args = ParsedArgs {
                             block_size,
                             cache_size,
-                            file_path: file_path.clone(),
+                            file_path: file_path.to_path_buf(),
                             map_strategy_factory: Box::new(FullyAssociativeFactory),
                             split_i_d: *split_i_d,
                             write_miss_policy,
                             write_policy: *write_policy,
+                            ..ParsedArgs::default()
                         };
 
                         let trace_sim = TraceSimulator::new(args)?;
@@ -287,11 +291,12 @@ fn find_best_configuration(
                             best_args = Some(ParsedArgs {
                                 block_size,
                                 cache_size,
-                                file_path: file_path.clone(),
+                                file_path: file_path.to_path_buf(),
                                 map_strategy_factory: Box::new(FullyAssociativeFactory),
                                 split_i_d: *split_i_d,
                                 write_miss_policy,
                                 write_policy: *write_policy,
+                                ..ParsedArgs::default()
                             });
                         }
                     }
@@ -346,6 +351,26 @@ fn show_results(
     println!("║ Data Refs         │ {:<33} ║", logs.data_references);
     println!("║ Instr Misses      │ {:<33} ║", logs.instruction_misses);
     println!("║ Data Misses       │ {:<33} ║", logs.data_misses);
+    for (name, kinds) in [
+        ("Instr", logs.instruction_miss_kinds),
+        ("Data", logs.data_miss_kinds),
+    ] {
+        println!(
+            "║ {:<17} │ {:<33} ║",
+            format!("{} Compulsory", name),
+            kinds.compulsory
+        );
+        println!(
+            "║ {:<17} │ {:<33} ║",
+            format!("{} Capacity", name),
+            kinds.capacity
+        );
+        println!(
+            "║ {:<17} │ {:<33} ║",
+            format!("{} Conflict", name),
+            kinds.conflict
+        );
+    }
     println!("║ Memory Reads      │ {:<33} ║", logs.memory_reads);
     println!("║ Memory Writes     │ {:<33} ║", logs.memory_writes);
     println!("║ Runtime           │ {:<33?} ║", logs.running_time);
@@ -374,8 +399,6 @@ fn print_usage(program_name: &str) {
     eprintln!("  -bs <size>          Lock block size");
     eprintln!("  -cs <size>          Lock cache size");
     eprintln!("  -wp <policy>      Lock write policy (writethrough/writeback)");
-    eprintln!(
-        "  -wmp <policy> Lock write miss policy (writeallocate/nowriteallocate)"
-    );
+    eprintln!("  -wmp <policy> Lock write miss policy (writeallocate/nowriteallocate)");
     eprintln!("  -split <bool>           Lock split I/D (true/false)");
 }
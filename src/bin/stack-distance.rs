@@ -0,0 +1,106 @@
+use cache_simulator::analysis::stack_distance::StackDistance;
+use cache_simulator::trace_reader::{RawRecord, TraceFormat, TraceReader};
+use cache_simulator::{DEFAULT_BLOCK_SIZE, WORD_SIZE};
+use std::error::Error;
+use std::path::PathBuf;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() < 2 {
+        print_usage(&args[0]);
+        return Ok(());
+    }
+
+    let block_sizes: Vec<usize> = match args.iter().position(|a| a == "-bs") {
+        Some(idx) => args
+            .get(idx + 1)
+            .ok_or("Missing value for -bs")?
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<_, _>>()?,
+        None => (0..=DEFAULT_BLOCK_SIZE.ilog2()).map(|x| 1 << x).collect(),
+    };
+    let format: Option<TraceFormat> = match args.iter().position(|a| a == "-format") {
+        Some(idx) => Some(
+            args.get(idx + 1)
+                .ok_or("Missing value for -format")?
+                .parse()?,
+        ),
+        None => None,
+    };
+    let word_size: usize = match args.iter().position(|a| a == "-ws") {
+        Some(idx) => args.get(idx + 1).ok_or("Missing value for -ws")?.parse()?,
+        None => WORD_SIZE,
+    };
+    if !word_size.is_power_of_two() {
+        return Err("The word size has to be a power of two".into());
+    }
+    let file_path = PathBuf::from(args.last().unwrap());
+
+    let mut analyses: Vec<StackDistance> = block_sizes
+        .iter()
+        .map(|&bs| StackDistance::new(bs))
+        .collect();
+
+    // Every record is split in the blocks it touches for each block size, so
+    // the trace is read only once
+    let mut reader = TraceReader::open(&file_path)?;
+    if let Some(format) = format {
+        reader.set_format(format);
+    }
+    reader.set_word_size(word_size);
+    while let Some(record) = reader.next_raw_record() {
+        let RawRecord::Access(_, access) = record? else {
+            continue;
+        };
+        for (&block_size, analysis) in block_sizes.iter().zip(analyses.iter_mut()) {
+            for address in reader.block_addresses(access, Some(block_size))? {
+                analysis.access(address);
+            }
+        }
+    }
+
+    for (block_size, analysis) in block_sizes.iter().zip(&analyses) {
+        show_results(*block_size, analysis, word_size);
+    }
+
+    Ok(())
+}
+
+fn show_results(block_size: usize, analysis: &StackDistance, word_size: usize) {
+    println!("Block Size: {}", block_size);
+    println!("┌──────────────────────────┬────────────────┐");
+    println!("│ Stack Distance           │ Accesses       │");
+    println!("├──────────────────────────┼────────────────┤");
+    println!(
+        "│ Cold                     │ {:<14} │",
+        analysis.cold_accesses()
+    );
+    for (range, count) in analysis.histogram().buckets() {
+        let label = if range.start() == range.end() {
+            range.start().to_string()
+        } else {
+            format!("{}-{}", range.start(), range.end())
+        };
+        println!("│ {:<24} │ {:<14} │", label, count);
+    }
+    println!("└──────────────────────────┴────────────────┘");
+
+    println!("┌────────────┬──────────────┬────────────────┐");
+    println!("│ Blocks     │ Bytes        │ Miss Ratio     │");
+    println!("├────────────┼──────────────┼────────────────┤");
+    for (blocks, miss_ratio) in analysis.miss_ratio_curve() {
+        let bytes = blocks * block_size * word_size;
+        println!("│ {:<10} │ {:<12} │ {:<14.6} │", blocks, bytes, miss_ratio);
+    }
+    println!("└────────────┴──────────────┴────────────────┘");
+}
+
+fn print_usage(program_name: &str) {
+    eprintln!("Usage: {} [options] <trace_file>", program_name);
+    eprintln!("Options:");
+    eprintln!("  -bs <sizes>         Block sizes to analyze, separated by commas");
+    eprintln!("  -ws <bytes>         Bytes of a word");
+    eprintln!("  -format <format>    Format of the trace, instead of the one its extension gives");
+}
@@ -0,0 +1,111 @@
+use cache_simulator::binary_trace::BinaryEncoder;
+use cache_simulator::cache::AccessType::{Read, Write};
+use cache_simulator::cache::ValueType::{Data, Instruction};
+use cache_simulator::trace_reader::{RawRecord, TraceFormat, TraceReader, ACCESS_SIZES};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write as _};
+use std::path::PathBuf;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() < 3 {
+        print_usage(&args[0]);
+        return Ok(());
+    }
+
+    let input = PathBuf::from(&args[args.len() - 2]);
+    let output = PathBuf::from(&args[args.len() - 1]);
+
+    let mut reader = TraceReader::open(&input)?;
+    if let Some(idx) = args.iter().position(|a| a == "-format") {
+        reader.set_format(
+            args.get(idx + 1)
+                .ok_or("Missing value for -format")?
+                .parse()?,
+        );
+    }
+    let threads = reader.threads()?;
+    let writer = BufWriter::new(File::create(&output)?);
+
+    let records = match TraceFormat::detect(&output) {
+        TraceFormat::Binary => write_binary(reader, writer, threads)?,
+        _ => write_text(reader, writer, threads)?,
+    };
+    println!("Converted {} records to {:?}", records, output);
+
+    Ok(())
+}
+
+/// Writes the records of `reader` to a binary trace, and returns how many
+/// there were.
+fn write_binary(
+    mut reader: TraceReader<io::BufReader<File>>,
+    writer: BufWriter<File>,
+    threads: Option<usize>,
+) -> Result<u64, Box<dyn Error>> {
+    let mut encoder = BinaryEncoder::new(writer, threads)?;
+    let mut records = 0;
+
+    while let Some(record) = reader.next_raw_record() {
+        encoder.write(&record?)?;
+        records += 1;
+    }
+
+    encoder.finish()?;
+    Ok(records)
+}
+
+/// Writes the records of `reader` to a text trace, and returns how many
+/// there were.
+fn write_text(
+    mut reader: TraceReader<io::BufReader<File>>,
+    mut writer: BufWriter<File>,
+    threads: Option<usize>,
+) -> Result<u64, Box<dyn Error>> {
+    let mut records = 0;
+    if let Some(threads) = threads {
+        writeln!(writer, "#threads {}", threads)?;
+    }
+
+    while let Some(record) = reader.next_raw_record() {
+        let RawRecord::Access(thread, (access_type, address, size)) = record? else {
+            return Err("Text traces can not hold flushes".into());
+        };
+
+        if threads.is_some() {
+            write!(writer, "{} ", thread)?;
+        }
+        let operation = match access_type {
+            Read(Data) => 0,
+            Write => 1,
+            Read(Instruction) => 2,
+        };
+        write!(writer, "{} {:x}", operation, address)?;
+        match size {
+            Some(size) if !ACCESS_SIZES.contains(&size) => {
+                return Err(format!("Text traces can not hold accesses of {} bytes", size).into())
+            }
+            Some(size) => writeln!(writer, " {}", size)?,
+            None => writeln!(writer)?,
+        }
+        records += 1;
+    }
+
+    writer.flush()?;
+    Ok(records)
+}
+
+fn print_usage(program_name: &str) {
+    eprintln!(
+        "Usage: {} [options] <input_trace> <output_trace>",
+        program_name
+    );
+    eprintln!("Options:");
+    eprintln!(
+        "  -format <format>    Format of the input trace, instead of the one its extension gives"
+    );
+    eprintln!();
+    eprintln!("Output traces ending in .bin are binary, and the rest text.");
+}
@@ -0,0 +1,237 @@
+use crate::cache::AccessType::{self, Read, Write};
+use crate::cache::ValueType::{Data, Instruction};
+use crate::trace_reader::RawRecord;
+use crate::MemoryAddress;
+use std::error::Error;
+use std::io::{self, Seek, SeekFrom};
+
+const MAGIC: &[u8; 4] = b"CTRC";
+const VERSION: u8 = 1;
+
+const OPERATION_MASK: u8 = 0b11;
+const FLUSH: u8 = 3;
+const HAS_SIZE: u8 = 1 << 2;
+const NEW_THREAD: u8 = 1 << 3;
+
+/// Writes records in the compact binary format of traces.
+///
+/// A binary trace starts with a header of the bytes `CTRC`, the version of
+/// the format, the threads of the trace as a little endian `u32` (`0` if it
+/// has no threads) and the records that follow as a little endian `u64`.
+///
+/// Every record starts with a byte that packs its operation in the two low
+/// bits, `0` for data reads, `1` for writes, `2` for instruction reads and
+/// `3` for flushes, a bit for a size and a bit for a change of thread. It is
+/// followed by the thread when it changed, the address as the difference
+/// with the address before it, and the size if there is one. Numbers are
+/// LEB128 varints, and the differences are zigzag encoded so small negative
+/// ones take few bytes too.
+pub struct BinaryEncoder<W> {
+    writer: W,
+    records: u64,
+    /// Address and thread of the last access.
+    address: MemoryAddress,
+    thread: usize,
+}
+
+impl<W: io::Write + Seek> BinaryEncoder<W> {
+    /// Writes the header of a trace of `threads` threads, or without threads
+    /// if there are none.
+    pub fn new(mut writer: W, threads: Option<usize>) -> io::Result<Self> {
+        let threads = u32::try_from(threads.unwrap_or(0))
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Too many threads"))?;
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&threads.to_le_bytes())?;
+        writer.write_all(&0u64.to_le_bytes())?;
+
+        Ok(Self {
+            writer,
+            records: 0,
+            address: 0,
+            thread: 0,
+        })
+    }
+
+    /// Writes `record`, whose size can be any number of bytes but `0`.
+    pub fn write(&mut self, record: &RawRecord) -> io::Result<()> {
+        let &RawRecord::Access(thread, (access_type, address, size)) = record else {
+            self.records += 1;
+            return self.writer.write_all(&[FLUSH]);
+        };
+        if size == Some(0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid access size: 0",
+            ));
+        }
+        self.records += 1;
+
+        let mut header = match access_type {
+            Read(Data) => 0,
+            Write => 1,
+            Read(Instruction) => 2,
+        };
+        if size.is_some() {
+            header |= HAS_SIZE;
+        }
+        if thread != self.thread {
+            header |= NEW_THREAD;
+        }
+        self.writer.write_all(&[header])?;
+
+        if thread != self.thread {
+            write_varint(&mut self.writer, thread as u64)?;
+            self.thread = thread;
+        }
+        let delta = address.wrapping_sub(self.address) as i64;
+        write_varint(&mut self.writer, ((delta << 1) ^ (delta >> 63)) as u64)?;
+        self.address = address;
+        if let Some(size) = size {
+            write_varint(&mut self.writer, size as u64)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the number of records to the header, and returns the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let position = self.writer.stream_position()?;
+        self.writer
+            .seek(SeekFrom::Start((MAGIC.len() + 1 + 4) as u64))?;
+        self.writer.write_all(&self.records.to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(position))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads the records of a binary trace.
+#[derive(Debug)]
+pub struct BinaryDecoder {
+    /// Records left to read.
+    records: u64,
+    /// Threads the header declares, `0` if the trace has no threads.
+    threads: usize,
+    address: MemoryAddress,
+    thread: usize,
+}
+
+impl BinaryDecoder {
+    /// Reads the header of a binary trace, and returns the decoder of its
+    /// records with the threads of the trace.
+    pub fn read_header(
+        reader: &mut impl io::Read,
+    ) -> Result<(Self, Option<usize>), Box<dyn Error>> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err("Not a binary trace".into());
+        }
+        let mut version = [0; 1];
+        reader.read_exact(&mut version)?;
+        if version[0] != VERSION {
+            return Err(format!("Unsupported binary trace version: {}", version[0]).into());
+        }
+
+        let mut threads = [0; 4];
+        reader.read_exact(&mut threads)?;
+        let threads = u32::from_le_bytes(threads) as usize;
+        let mut records = [0; 8];
+        reader.read_exact(&mut records)?;
+
+        let decoder = Self {
+            records: u64::from_le_bytes(records),
+            threads,
+            address: 0,
+            thread: 0,
+        };
+        Ok((decoder, (threads > 0).then_some(threads)))
+    }
+
+    pub fn read_record(
+        &mut self,
+        reader: &mut impl io::Read,
+    ) -> Option<Result<RawRecord, Box<dyn Error>>> {
+        if self.records == 0 {
+            return None;
+        }
+        self.records -= 1;
+
+        let record = match self.decode(reader) {
+            Ok(record) => record,
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                return Some(Err("The binary trace ends before all its records".into()))
+            }
+            Err(err) => return Some(Err(err.into())),
+        };
+
+        Some(match record {
+            RawRecord::Access(thread, _) if thread >= self.threads.max(1) => Err(format!(
+                "Thread {} is not one of the {} threads",
+                thread,
+                self.threads.max(1)
+            )
+            .into()),
+            RawRecord::Access(_, (_, _, Some(0))) => Err("Invalid access size: 0".into()),
+            record => Ok(record),
+        })
+    }
+
+    fn decode(&mut self, reader: &mut impl io::Read) -> io::Result<RawRecord> {
+        let mut header = [0; 1];
+        reader.read_exact(&mut header)?;
+        let header = header[0];
+
+        let access_type: AccessType = match header & OPERATION_MASK {
+            0 => Read(Data),
+            1 => Write,
+            2 => Read(Instruction),
+            _ => return Ok(RawRecord::Flush),
+        };
+        if header & NEW_THREAD != 0 {
+            self.thread = read_varint(reader)? as usize;
+        }
+        let zigzag = read_varint(reader)?;
+        let delta = (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+        self.address = self.address.wrapping_add(delta as MemoryAddress);
+        let size = if header & HAS_SIZE != 0 {
+            Some(read_varint(reader)? as usize)
+        } else {
+            None
+        };
+
+        Ok(RawRecord::Access(
+            self.thread,
+            (access_type, self.address, size),
+        ))
+    }
+}
+
+fn write_varint(writer: &mut impl io::Write, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint(reader: &mut impl io::Read) -> io::Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0; 1];
+        reader.read_exact(&mut byte)?;
+        value |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "Varint too long",
+    ))
+}
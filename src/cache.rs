@@ -1,13 +1,42 @@
 use crate::cache_block::CacheBlock;
+use crate::coherence::{Bus, BusTransaction, CoherenceState, SnoopReply};
 use crate::logger::Logger;
 use crate::map_strategies::{MapStrategy, MapStrategyFactory};
-use crate::MemoryAddress;
+use crate::miss_classifier::MissClassifier;
+use crate::prefetchers::{Outcome, Prefetcher, PrefetcherFactory};
+use crate::replacement_policies::ReplacementPolicyFactory;
+use crate::victim_cache::VictimCache;
+use crate::{MemoryAddress, HIT_DURATION, WORD_SIZE};
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
+use std::time::Duration;
 use AccessType::*;
+use InclusionPolicy::*;
+use ValueType::*;
 use WriteMissPolicy::*;
 use WritePolicy::*;
 
+/// A block that a level evicted and the levels above it have to drop.
+struct BackInvalidation {
+    address: MemoryAddress,
+    words: u128,
+    /// Where a dirty copy of the block goes, or main memory if there is none.
+    write_back_to: Option<Rc<RefCell<Cache>>>,
+}
+
+/// Blocks the levels below a cache asked it to invalidate.
+type Invalidations = Rc<RefCell<Vec<BackInvalidation>>>;
+
+/// Connection of a private cache to the bus it shares with other cores.
+struct Snooping {
+    core: usize,
+    bus: Rc<RefCell<Bus>>,
+    /// Blocks other cores invalidated, so the next miss on them is a
+    /// coherence miss, with the time of the invalidation.
+    invalidated: HashMap<MemoryAddress, u128>,
+}
+
 /// Represents the main cache structure.
 pub struct Cache {
     block_size: usize, // Bytes
@@ -15,6 +44,20 @@ pub struct Cache {
     blocks: Box<[CacheBlock]>,
     write_policy: WritePolicy,
     on_write_miss: WriteMissPolicy,
+    latency: Duration,
+    inclusion: InclusionPolicy,
+    miss_classifier: MissClassifier,
+    victim_cache: Option<VictimCache>,
+    prefetcher: Option<Box<dyn Prefetcher>>,
+    /// Blocks brought by a prefetch and not used yet, with the time they
+    /// arrive.
+    prefetched: HashMap<usize, Duration>,
+    /// Cache that serves the misses, or main memory if there is none.
+    next_level: Option<Rc<RefCell<Cache>>>,
+    invalidations: Invalidations,
+    /// Invalidations of every level above this one.
+    upper_levels: Vec<Invalidations>,
+    snooping: Option<Snooping>,
     log: Rc<RefCell<Logger>>,
 }
 
@@ -23,11 +66,13 @@ impl Cache {
         block_size: usize,
         cache_size: usize,
         map_strategy_factory: &dyn MapStrategyFactory,
+        replacement_policy_factory: &dyn ReplacementPolicyFactory,
         write_policy: WritePolicy,
         on_write_miss: WriteMissPolicy,
         log: Rc<RefCell<Logger>>,
     ) -> Self {
-        let map_strategy = map_strategy_factory.generate(block_size, cache_size);
+        let map_strategy =
+            map_strategy_factory.generate(block_size, cache_size, replacement_policy_factory);
         let blocks = vec![CacheBlock::default(); cache_size].into_boxed_slice();
 
         Cache {
@@ -36,61 +81,738 @@ impl Cache {
             blocks,
             write_policy,
             on_write_miss,
+            latency: HIT_DURATION,
+            inclusion: InclusionPolicy::default(),
+            miss_classifier: MissClassifier::new(block_size, cache_size),
+            victim_cache: None,
+            prefetcher: None,
+            prefetched: HashMap::new(),
+            next_level: None,
+            invalidations: Rc::default(),
+            upper_levels: Vec::new(),
+            snooping: None,
             log,
         }
     }
 
-    /// Retrieves a mutable reference to a cache block based on the address.
-    fn get_block(&mut self, address: MemoryAddress) -> &mut CacheBlock {
-        let block_index = self.map_strategy.map(address, &self.blocks);
-        &mut self.blocks[block_index as usize]
+    /// Sets the time each access to this cache takes.
+    pub fn set_latency(&mut self, latency: Duration) {
+        self.latency = latency;
+    }
+
+    /// Sets how the blocks of this cache relate to the blocks of the levels
+    /// above it.
+    pub fn set_inclusion(&mut self, inclusion: InclusionPolicy) {
+        self.inclusion = inclusion;
+    }
+
+    /// Adds a victim cache with room for `entries` blocks, which receives the
+    /// blocks this cache evicts and is probed on every miss.
+    pub fn set_victim_cache(&mut self, entries: usize) {
+        self.victim_cache = Some(VictimCache::new(entries));
+        self.log.borrow_mut().victim_hits.get_or_insert(0);
+    }
+
+    /// Adds a prefetcher generated by `prefetcher_factory`, which sees every
+    /// demand access and places the blocks it asks for.
+    pub fn set_prefetcher(&mut self, prefetcher_factory: &dyn PrefetcherFactory) {
+        self.prefetcher = Some(prefetcher_factory.generate(self.block_size));
+        self.log
+            .borrow_mut()
+            .prefetches
+            .get_or_insert_with(Default::default);
+    }
+
+    /// Sends the misses and write-backs of this cache to `next_level`
+    /// instead of main memory.
+    ///
+    /// The levels below have to be connected before the ones above them, so
+    /// every inclusive level knows which caches it has to invalidate.
+    pub fn set_next_level(&mut self, next_level: Rc<RefCell<Cache>>) {
+        let mut level = Some(Rc::clone(&next_level));
+        while let Some(current) = level {
+            let mut current = current.borrow_mut();
+            current.upper_levels.push(Rc::clone(&self.invalidations));
+            level = current.next_level.clone();
+        }
+
+        self.next_level = Some(next_level);
+    }
+
+    /// Makes this cache the private cache of `core`, kept coherent with the
+    /// other caches on `bus`. Use [`Bus::connect`] instead of calling this.
+    pub fn set_bus(&mut self, core: usize, bus: Rc<RefCell<Bus>>) {
+        self.snooping = Some(Snooping {
+            core,
+            bus,
+            invalidated: HashMap::new(),
+        });
+        self.log
+            .borrow_mut()
+            .coherence
+            .get_or_insert_with(Default::default);
+    }
+
+    /// Returns the bits the tags of this cache and its victim cache take
+    /// with addresses of `address_width` bits.
+    pub fn tag_storage(&self, address_width: u32) -> u128 {
+        let offset_bits = (self.block_size * WORD_SIZE).ilog2();
+        let tag_bits = address_width.saturating_sub(offset_bits) as u128;
+        let index_bits = self.map_strategy.index_bits() as u128;
+        let victim_entries = self.victim_cache.as_ref().map_or(0, VictimCache::entries);
+
+        self.blocks.len() as u128 * tag_bits.saturating_sub(index_bits)
+            + victim_entries as u128 * tag_bits
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Returns the words this cache and its victim cache can hold.
+    pub fn capacity(&self) -> usize {
+        let victim_entries = self.victim_cache.as_ref().map_or(0, VictimCache::entries);
+        (self.blocks.len() + victim_entries) * self.block_size
+    }
+
+    /// Returns the address of every valid block, including the ones in the
+    /// victim cache.
+    pub fn stored_blocks(&self) -> impl Iterator<Item = MemoryAddress> + '_ {
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| block.valid)
+            .map(|(block_index, block)| self.map_strategy.get_address(block.tag, block_index))
+            .chain(self.victim_cache.iter().flat_map(VictimCache::blocks))
+    }
+
+    /// Retrieves the index of the cache block where the address should be.
+    fn get_block_index(&mut self, address: MemoryAddress) -> usize {
+        self.map_strategy.map(address, &self.blocks) as usize
     }
 
     pub fn access(&mut self, access_type: AccessType, address: MemoryAddress) {
+        self.access_block(access_type, address, false);
+
+        if let (Write, Some(snooping)) = (access_type, &self.snooping) {
+            snooping.bus.borrow().record_write(snooping.core, address);
+        }
+    }
+
+    /// Accesses the block of `address`. When `whole_block` is set the access
+    /// writes the entire block, so a write miss does not need to read it.
+    ///
+    /// Returns whether a read handed over a dirty block, which only happens
+    /// when this level is exclusive.
+    fn access_block(
+        &mut self,
+        access_type: AccessType,
+        address: MemoryAddress,
+        whole_block: bool,
+    ) -> bool {
         let rc_log = Rc::clone(&self.log);
         let mut log = rc_log.borrow_mut();
 
+        self.back_invalidate(&mut log);
         log.reference(&access_type);
 
         let bs = self.block_size as u128;
-        let write_policy = self.write_policy;
-        let on_write_miss = self.on_write_miss;
+        let block_bytes = (self.block_size * WORD_SIZE) as MemoryAddress;
+        let block_address = address - address % block_bytes;
+        let exclusive = matches!(self.inclusion, Exclusive);
         let tag = self.map_strategy.get_tag(address);
-        let block = self.get_block(address);
+        let allocates = match access_type {
+            Read(_) => !exclusive,
+            Write => !exclusive && matches!(self.on_write_miss, WriteAllocate),
+        };
+        let block_index = if allocates {
+            Some(self.get_block_index(address))
+        } else {
+            // Exclusive levels only place the victims of the level above, and
+            // write misses without allocation place nothing, so the policy
+            // only hears about the hits
+            let block_index = self.map_strategy.find(address, &self.blocks);
+            if let (Some(block_index), false) = (block_index, exclusive) {
+                self.map_strategy.touch(block_index);
+            }
+            block_index
+        };
 
-        if block.valid && block.is_match(tag) {
+        if let Some(block_index) = block_index.filter(|&idx| {
+            let block = &self.blocks[idx];
+            block.valid && block.is_match(tag)
+        }) {
             // HIT
+            self.miss_classifier.hit(address);
+            let outcome = match self.prefetched.remove(&block_index) {
+                Some(arrival) => {
+                    let wait = arrival.saturating_sub(self.demand_time(&log));
+                    log.useful_prefetch(wait);
+                    Outcome::PrefetchHit
+                }
+                None => Outcome::Hit,
+            };
+
+            let mut dirty = false;
             if let Write = access_type {
-                match write_policy {
-                    WriteThrough => log.memory_write(1),
-                    WriteBack => block.dirty = true,
+                let state = self.blocks[block_index].state;
+                self.gain_ownership(&mut log, address, state);
+            }
+            match access_type {
+                Write => match self.write_policy {
+                    WriteThrough => self.write_next_level(&mut log, address, 1),
+                    WriteBack => self.blocks[block_index].dirty = true,
+                },
+                Read(_) if exclusive => {
+                    // The block moves to the level above
+                    dirty = self.blocks[block_index].dirty;
+                    self.invalidate_block(&mut log, block_index);
                 }
+                Read(_) => {}
             }
 
-            log.hit();
-            return;
+            log.hit(self.latency);
+            self.prefetch(&mut log, address, outcome);
+            self.back_invalidate(&mut log);
+            return dirty;
         }
 
         // MISS
-        match (access_type, on_write_miss) {
-            (Read(_), _) | (Write, WriteAllocate) => {
-                block.tag = tag;
-                block.valid = true;
+        let miss_kind = self.miss_classifier.miss(address);
+        if let Some(snooping) = self.snooping.as_mut() {
+            if let Some(since) = snooping.invalidated.remove(&block_address) {
+                let sharing = snooping.bus.borrow().classify_sharing(
+                    snooping.core,
+                    address,
+                    block_bytes,
+                    since,
+                );
+                log.coherence_miss(sharing);
+            }
+        }
+        let mut dirty = false;
+        match (access_type, self.on_write_miss, block_index) {
+            (Read(_), _, None) => match self.take_victim(&mut log, block_address) {
+                Some(victim_dirty) => dirty = victim_dirty,
+                None => dirty = self.read_next_level(&mut log, access_type, address, bs),
+            },
+            (Read(_), _, Some(block_index)) | (Write, WriteAllocate, Some(block_index)) => {
+                let victim_dirty = self.take_victim(&mut log, block_address);
+                let dirty = matches!(access_type, Write) || victim_dirty == Some(true);
+                self.place(&mut log, block_index, tag, dirty);
+
+                let supplied = match self.snooping.is_some() {
+                    true => self.fetch_shared(&mut log, block_index, access_type, block_address),
+                    false => false,
+                };
+
+                if victim_dirty.is_none()
+                    && !supplied
+                    && !(whole_block && matches!(access_type, Write))
+                {
+                    // The block is read before writing on it
+                    let fetch_type = match access_type {
+                        Write => Read(Data),
+                        read => read,
+                    };
+                    if self.read_next_level(&mut log, fetch_type, block_address, bs) {
+                        self.blocks[block_index].dirty = true;
+                    }
+                }
+            }
+            (Write, _, _) => {
+                self.gain_ownership(&mut log, address, CoherenceState::Invalid);
+                let in_victim_cache = self
+                    .victim_cache
+                    .as_mut()
+                    .is_some_and(|victim_cache| victim_cache.write(block_address));
+
+                if in_victim_cache {
+                    log.victim_hit();
+                }
+                if !in_victim_cache || matches!(self.write_policy, WriteThrough) {
+                    let words = if whole_block && exclusive { bs } else { 1 };
+                    self.write_next_level(&mut log, address, words);
+                }
+            }
+        }
+
+        log.miss(&access_type, miss_kind);
+        log.hit(self.latency);
+        self.prefetch(&mut log, address, Outcome::Miss);
+        self.back_invalidate(&mut log);
+        dirty
+    }
+
+    /// Places the block with `tag` in `block_index`, evicting the block that
+    /// was there.
+    fn place(&mut self, log: &mut Logger, block_index: usize, tag: MemoryAddress, dirty: bool) {
+        self.map_strategy.place(block_index, tag, &self.blocks);
+        let block = &mut self.blocks[block_index];
+        let evicted = block.valid.then_some((block.tag, block.dirty));
+
+        block.tag = tag;
+        block.valid = true;
+        block.dirty = dirty;
+
+        if self.prefetched.remove(&block_index).is_some() {
+            log.prefetch_pollution();
+        }
+        if let Some((evicted_tag, evicted_dirty)) = evicted {
+            let evicted_address = self.map_strategy.get_address(evicted_tag, block_index);
+            self.evict(log, evicted_address, evicted_dirty);
+        }
+    }
+
+    /// Tells the prefetcher about a demand access to `address`, and places
+    /// the blocks it asks for.
+    ///
+    /// Exclusive levels do not prefetch, as they only hold the victims of the
+    /// level above.
+    fn prefetch(&mut self, log: &mut Logger, address: MemoryAddress, outcome: Outcome) {
+        let Some(prefetcher) = self.prefetcher.as_mut() else {
+            return;
+        };
+        if matches!(self.inclusion, Exclusive) {
+            return;
+        }
+        let addresses = prefetcher.access(address, outcome);
+
+        let bs = self.block_size as u128;
+        let block_bytes = (self.block_size * WORD_SIZE) as MemoryAddress;
+        for address in addresses {
+            let address = address - address % block_bytes;
+            let in_victim_cache = self
+                .victim_cache
+                .as_ref()
+                .is_some_and(|victim_cache| victim_cache.contains(address));
+            if in_victim_cache || self.map_strategy.find(address, &self.blocks).is_some() {
+                continue;
+            }
+
+            // Prefetches happen in the background, so their time is taken
+            // back from every level and only the accesses that wait for them
+            // pay it
+            let start = self.demand_time(log);
+            let running_times = self.running_times(log);
+
+            let tag = self.map_strategy.get_tag(address);
+            let block_index = self.get_block_index(address);
+            self.place(log, block_index, tag, false);
+            if self.read_next_level(log, Read(Data), address, bs) {
+                self.blocks[block_index].dirty = true;
+            }
+
+            let fetch_time = self.restore_running_times(log, &running_times);
+            self.prefetched.insert(block_index, start + fetch_time);
+            log.prefetch_issued();
+        }
+    }
+
+    /// Returns the time spent on the demand accesses of this cache and the
+    /// levels below it, given the log of this cache.
+    fn demand_time(&self, log: &Logger) -> Duration {
+        self.running_times(log).into_iter().sum()
+    }
+
+    /// Returns the running time of this cache and of every level below it,
+    /// given the log of this cache.
+    fn running_times(&self, log: &Logger) -> Vec<Duration> {
+        let mut running_times = vec![log.running_time];
+        if let Some(next_level) = &self.next_level {
+            let next_level = next_level.borrow();
+            let next_log = next_level.log.borrow();
+            running_times.extend(next_level.running_times(&next_log));
+        }
+
+        running_times
+    }
+
+    /// Sets the running time of this cache and of every level below it back
+    /// to `running_times`, and returns the time they had spent since.
+    fn restore_running_times(&self, log: &mut Logger, running_times: &[Duration]) -> Duration {
+        let spent = log.running_time - running_times[0];
+        log.running_time = running_times[0];
+
+        spent
+            + match &self.next_level {
+                Some(next_level) => {
+                    let next_level = next_level.borrow();
+                    let mut next_log = next_level.log.borrow_mut();
+                    next_level.restore_running_times(&mut next_log, &running_times[1..])
+                }
+                None => Duration::ZERO,
+            }
+    }
+
+    /// Takes the block at `address` out of the victim cache, and returns
+    /// whether it was dirty.
+    fn take_victim(&mut self, log: &mut Logger, address: MemoryAddress) -> Option<bool> {
+        let victim_dirty = self.victim_cache.as_mut()?.take(address)?;
+        log.victim_hit();
+        Some(victim_dirty)
+    }
+
+    /// Handles a block evicted from this cache, which goes to the victim
+    /// cache if there is one.
+    fn evict(&mut self, log: &mut Logger, address: MemoryAddress, dirty: bool) {
+        let (address, dirty) = match self.victim_cache.as_mut() {
+            Some(victim_cache) => match victim_cache.insert(address, dirty) {
+                Some(evicted) => evicted,
+                None => return,
+            },
+            None => (address, dirty),
+        };
+
+        self.release(log, address, dirty);
+    }
+
+    /// Handles a block that leaves this level.
+    fn release(&self, log: &mut Logger, address: MemoryAddress, dirty: bool) {
+        let bs = self.block_size as u128;
+
+        if let Some(ref snooping) = self.snooping {
+            snooping.bus.borrow().evicted(snooping.core, address);
+        }
+        let dirty = dirty && matches!(self.write_policy, WriteBack);
+
+        if let Inclusive = self.inclusion {
+            for invalidations in &self.upper_levels {
+                invalidations.borrow_mut().push(BackInvalidation {
+                    address,
+                    words: bs,
+                    write_back_to: self.next_level.clone(),
+                });
+            }
+        }
+
+        match &self.next_level {
+            Some(next_level) if matches!(next_level.borrow().inclusion, Exclusive) => {
+                log.next_level_write(bs);
+                next_level.borrow_mut().place_victim(address, dirty);
+            }
+            _ if dirty => self.write_next_level(log, address, bs),
+            _ => {}
+        }
+    }
+
+    /// Places a block evicted by the level above, as exclusive levels do.
+    fn place_victim(&mut self, address: MemoryAddress, dirty: bool) {
+        let rc_log = Rc::clone(&self.log);
+        let mut log = rc_log.borrow_mut();
+
+        self.back_invalidate(&mut log);
+
+        let tag = self.map_strategy.get_tag(address);
+        let block_index = self.get_block_index(address);
+        let block = &mut self.blocks[block_index];
+
+        if block.valid && block.is_match(tag) {
+            block.dirty |= dirty;
+            return;
+        }
+
+        self.place(&mut log, block_index, tag, dirty);
+        self.back_invalidate(&mut log);
+    }
+
+    /// Drops the blocks that an inclusive level below evicted, writing the
+    /// dirty ones to where that level sends its write-backs.
+    fn back_invalidate(&mut self, log: &mut Logger) {
+        let block_bytes = (self.block_size * WORD_SIZE) as MemoryAddress;
+
+        loop {
+            let invalidations = self.invalidations.take();
+            if invalidations.is_empty() {
+                return;
+            }
+
+            for invalidation in invalidations {
+                let end = invalidation.address
+                    + (invalidation.words as MemoryAddress) * WORD_SIZE as MemoryAddress;
+                let mut block_address = invalidation.address - invalidation.address % block_bytes;
+
+                while block_address < end {
+                    let dirty = match self.map_strategy.find(block_address, &self.blocks) {
+                        Some(block_index) => {
+                            let dirty = self.blocks[block_index].dirty;
+                            self.invalidate_block(log, block_index);
+                            Some(dirty)
+                        }
+                        None => self
+                            .victim_cache
+                            .as_mut()
+                            .and_then(|victim_cache| victim_cache.take(block_address)),
+                    };
+
+                    if let Some(dirty) = dirty {
+                        log.back_invalidation();
+                        if dirty && matches!(self.write_policy, WriteBack) {
+                            write_to(
+                                log,
+                                &invalidation.write_back_to,
+                                block_address,
+                                self.block_size as u128,
+                            );
+                        }
+                    }
+                    block_address += block_bytes;
+                }
+            }
+        }
+    }
+
+    fn invalidate_block(&mut self, log: &mut Logger, block_index: usize) {
+        self.map_strategy.invalidate(block_index, &self.blocks);
+        if self.prefetched.remove(&block_index).is_some() {
+            log.prefetch_pollution();
+        }
+        let block = &mut self.blocks[block_index];
+        block.valid = false;
+        block.dirty = false;
+        block.state = CoherenceState::Invalid;
+    }
+
+    /// Tells the other caches on the bus that the block at `address`, which
+    /// this cache holds in `state`, is about to be written.
+    fn gain_ownership(&mut self, log: &mut Logger, address: MemoryAddress, state: CoherenceState) {
+        let Some(snooping) = self.snooping.as_ref() else {
+            return;
+        };
+
+        let bus = snooping.bus.borrow();
+        let transaction = match state {
+            CoherenceState::Invalid => Some(BusTransaction::Upgrade),
+            state => bus.protocol.write_hit(state),
+        };
+        if let Some(transaction) = transaction {
+            log.bus_transaction(transaction);
+            bus.broadcast(snooping.core, transaction, address);
+        }
+        drop(bus);
+
+        if let Some(block_index) = self.map_strategy.find(address, &self.blocks) {
+            self.blocks[block_index].state = CoherenceState::Modified;
+        }
+    }
+
+    /// Asks the other caches on the bus for the block at `address`, which a
+    /// miss just placed in `block_index`, and sets its state.
+    ///
+    /// Returns whether another cache supplied the block, so it is not read
+    /// from memory.
+    fn fetch_shared(
+        &mut self,
+        log: &mut Logger,
+        block_index: usize,
+        access_type: AccessType,
+        address: MemoryAddress,
+    ) -> bool {
+        let Some(snooping) = self.snooping.as_ref() else {
+            return false;
+        };
+
+        let bus = snooping.bus.borrow();
+        let transaction = match access_type {
+            Write => BusTransaction::ReadExclusive,
+            Read(_) => BusTransaction::Read,
+        };
+        log.bus_transaction(transaction);
+        let reply = bus.broadcast(snooping.core, transaction, address);
+
+        self.blocks[block_index].state = match access_type {
+            Write => CoherenceState::Modified,
+            Read(_) => bus.protocol.read_miss(reply.shared),
+        };
+        reply.supplied
+    }
+
+    /// Handles `transaction` for the block at `address`, sent by another cache
+    /// on the bus.
+    pub fn snoop(&mut self, transaction: BusTransaction, address: MemoryAddress) -> SnoopReply {
+        let Some(snooping) = self.snooping.as_ref() else {
+            return SnoopReply::default();
+        };
+        let Some(block_index) = self.map_strategy.find(address, &self.blocks) else {
+            return SnoopReply::default();
+        };
+        let block = &self.blocks[block_index];
+        if !block.valid || block.state == CoherenceState::Invalid {
+            return SnoopReply::default();
+        }
+
+        let action = snooping
+            .bus
+            .borrow()
+            .protocol
+            .snoop(block.state, transaction);
+        let rc_log = Rc::clone(&self.log);
+        let mut log = rc_log.borrow_mut();
+        let block_bytes = (self.block_size * WORD_SIZE) as MemoryAddress;
+        let block_address = address - address % block_bytes;
 
-                if matches!(write_policy, WriteBack) && block.valid && block.dirty {
-                    log.memory_write(bs);
+        if action.flush && block.dirty {
+            self.write_next_level(&mut log, block_address, self.block_size as u128);
+            self.blocks[block_index].dirty = false;
+        }
+        if action.supply {
+            log.intervention();
+        }
+        if action.next_state == CoherenceState::Invalid {
+            self.invalidate_coherent_block(&mut log, block_index, block_address);
+        } else {
+            self.blocks[block_index].state = action.next_state;
+        }
+
+        SnoopReply {
+            shared: true,
+            supplied: action.supply,
+            owned: matches!(
+                action.next_state,
+                CoherenceState::Modified | CoherenceState::Owned | CoherenceState::Exclusive
+            ),
+        }
+    }
+
+    /// Drops the block at `address` because the directory has no room to
+    /// record this cache as one of its sharers, writing it back if dirty.
+    pub fn recall(&mut self, address: MemoryAddress) {
+        let Some(block_index) = self.map_strategy.find(address, &self.blocks) else {
+            return;
+        };
+        let rc_log = Rc::clone(&self.log);
+        let mut log = rc_log.borrow_mut();
+        let block_bytes = (self.block_size * WORD_SIZE) as MemoryAddress;
+        let block_address = address - address % block_bytes;
+
+        if self.blocks[block_index].dirty && matches!(self.write_policy, WriteBack) {
+            self.write_next_level(&mut log, block_address, self.block_size as u128);
+        }
+        self.invalidate_coherent_block(&mut log, block_index, block_address);
+    }
+
+    /// Empties this cache and its victim cache, writing back the dirty
+    /// blocks.
+    pub fn flush(&mut self) {
+        let rc_log = Rc::clone(&self.log);
+        let mut log = rc_log.borrow_mut();
+        let bs = self.block_size as u128;
+        let write_back = matches!(self.write_policy, WriteBack);
+
+        for block_index in 0..self.blocks.len() {
+            let block = &self.blocks[block_index];
+            if !block.valid {
+                continue;
+            }
+            let address = self.map_strategy.get_address(block.tag, block_index);
+            let dirty = block.dirty;
+
+            self.invalidate_block(&mut log, block_index);
+            if let Some(ref snooping) = self.snooping {
+                snooping.bus.borrow().evicted(snooping.core, address);
+            }
+            if dirty && write_back {
+                self.write_next_level(&mut log, address, bs);
+            }
+        }
+
+        if let Some(victim_cache) = self.victim_cache.as_mut() {
+            let victims: Vec<_> = victim_cache.blocks().collect();
+            for address in victims {
+                let dirty = victim_cache.take(address).unwrap_or(false);
+                if dirty && write_back {
+                    write_to(&mut log, &self.next_level, address, bs);
                 }
+            }
+        }
+    }
+
+    /// Invalidates a block because of another core, so the next miss on it
+    /// is a coherence miss.
+    fn invalidate_coherent_block(
+        &mut self,
+        log: &mut Logger,
+        block_index: usize,
+        block_address: MemoryAddress,
+    ) {
+        self.invalidate_block(log, block_index);
+        log.coherence_invalidation();
+        if let Some(snooping) = self.snooping.as_mut() {
+            let now = snooping.bus.borrow().now();
+            snooping.invalidated.insert(block_address, now);
+        }
+    }
 
-                log.memory_read(bs);
-                block.dirty = matches!(access_type, Write);
+    /// Reads `words` words starting at `address` from the next level, and
+    /// returns whether they came from a dirty block.
+    fn read_next_level(
+        &self,
+        log: &mut Logger,
+        access_type: AccessType,
+        address: MemoryAddress,
+        words: u128,
+    ) -> bool {
+        match &self.next_level {
+            Some(next_level) => {
+                log.next_level_read(words);
+                next_level
+                    .borrow_mut()
+                    .access_words(access_type, address, words)
             }
-            (Write, NoWriteAllocate) => {
-                log.memory_write(1);
+            None => {
+                log.memory_read(words);
+                false
             }
         }
+    }
 
-        log.miss(&access_type);
-        log.hit();
+    /// Writes `words` words starting at `address` to the next level.
+    fn write_next_level(&self, log: &mut Logger, address: MemoryAddress, words: u128) {
+        write_to(log, &self.next_level, address, words);
+    }
+
+    /// Accesses every block touched by `words` words starting at `address`,
+    /// as an upper level does when it reads or writes back a block.
+    fn access_words(
+        &mut self,
+        access_type: AccessType,
+        address: MemoryAddress,
+        words: u128,
+    ) -> bool {
+        let block_bytes = (self.block_size * WORD_SIZE) as MemoryAddress;
+        let end = address + (words as MemoryAddress) * WORD_SIZE as MemoryAddress;
+        let mut block_address = address - address % block_bytes;
+        let mut dirty = false;
+
+        while block_address < end {
+            let whole_block = block_address >= address && block_address + block_bytes <= end;
+            dirty |= self.access_block(access_type, block_address.max(address), whole_block);
+            block_address += block_bytes;
+        }
+
+        dirty
+    }
+
+    /// Adds the statistics collected by the mapping strategy to the log.
+    pub fn report(&self) {
+        self.map_strategy.report(&mut self.log.borrow_mut());
+    }
+}
+
+/// Writes `words` words starting at `address` to `level`, or to main memory
+/// if there is no level.
+fn write_to(
+    log: &mut Logger,
+    level: &Option<Rc<RefCell<Cache>>>,
+    address: MemoryAddress,
+    words: u128,
+) {
+    match level {
+        Some(level) => {
+            log.next_level_write(words);
+            level.borrow_mut().access_words(Write, address, words);
+        }
+        None => log.memory_write(words),
     }
 }
 
@@ -108,6 +830,21 @@ pub enum WriteMissPolicy {
     NoWriteAllocate,
 }
 
+/// Defines which blocks of the levels above a cache are also in it.
+#[derive(Default, Clone, Copy, Debug)]
+pub enum InclusionPolicy {
+    /// Every block of the levels above is also here. Evicting a block
+    /// invalidates the copies above it.
+    Inclusive,
+    /// No block of the level above is here. The level is only filled with
+    /// the blocks the level above evicts, and hands its blocks over on a hit.
+    Exclusive,
+    /// Non-inclusive non-exclusive (NINE). Blocks are placed on every miss,
+    /// but evicting them does not affect the levels above.
+    #[default]
+    NonInclusive,
+}
+
 #[derive(Default, Clone, Copy, Debug)]
 pub enum WritePolicy {
     /// Write data to both the cache and main memory on every write.
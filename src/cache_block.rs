@@ -1,3 +1,4 @@
+use crate::coherence::CoherenceState;
 use crate::MemoryAddress;
 
 /// Represents a single block within the cache.
@@ -6,6 +7,8 @@ pub struct CacheBlock {
     pub valid: bool,
     pub dirty: bool,
     pub tag: MemoryAddress,
+    /// Coherence state of the block. Only caches connected to a bus keep it.
+    pub state: CoherenceState,
 }
 
 impl CacheBlock {
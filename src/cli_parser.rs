@@ -1,22 +1,76 @@
-use crate::cache::{WriteMissPolicy, WritePolicy};
+use crate::cache::{InclusionPolicy, WriteMissPolicy, WritePolicy};
+use crate::coherence::directory::SharerFormat;
+use crate::coherence::mesi::Mesi;
+use crate::coherence::moesi::Moesi;
+use crate::coherence::msi::Msi;
+use crate::coherence::CoherenceProtocol;
 use crate::map_strategies::direct_map::DirectMapFactory;
 use crate::map_strategies::fully_associative::FullyAssociativeFactory;
 use crate::map_strategies::set_associative::SetAssociativeFactory;
 use crate::map_strategies::MapStrategyFactory;
-use crate::{DEFAULT_BLOCK_SIZE, DEFAULT_CACHE_SIZE};
+use crate::prefetchers::next_line::NextLineFactory;
+use crate::prefetchers::stream::StreamFactory;
+use crate::prefetchers::stride::StrideFactory;
+use crate::prefetchers::PrefetcherFactory;
+use crate::replacement_policies::arc::ArcFactory;
+use crate::replacement_policies::clock::ClockFactory;
+use crate::replacement_policies::fifo::FifoFactory;
+use crate::replacement_policies::lfu::{LfruFactory, LfuFactory};
+use crate::replacement_policies::lru::{BipFactory, DipFactory, LipFactory, LruFactory};
+use crate::replacement_policies::opt::OptFactory;
+use crate::replacement_policies::plru::{BitPlruFactory, TreePlruFactory};
+use crate::replacement_policies::random::RandomFactory;
+use crate::replacement_policies::rrip::{BrripFactory, DrripFactory, SrripFactory};
+use crate::replacement_policies::ReplacementPolicyFactory;
+use crate::trace_reader::TraceFormat;
+use crate::{
+    MemoryAddress, DEFAULT_BIP_EPSILON, DEFAULT_BLOCK_SIZE, DEFAULT_BRRIP_PROBABILITY,
+    DEFAULT_CACHE_SIZE, DEFAULT_LOWER_LEVEL_LATENCY, DEFAULT_PREFETCH_DEGREE,
+    DEFAULT_PREFETCH_STREAMS, DEFAULT_RRPV_BITS, DEFAULT_SEED, HIT_DURATION, WORD_SIZE,
+};
 use std::env::Args;
 use std::error::Error;
 use std::fmt::Display;
 use std::path::PathBuf;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::Duration;
 
 #[derive(Debug)]
 pub struct ParsedArgs {
     pub block_size: usize,
+    /// Bytes of the words the block size is given in, which the trace
+    /// addresses are read with.
+    pub word_size: usize,
     pub cache_size: usize,
     pub write_policy: WritePolicy,
     pub write_miss_policy: WriteMissPolicy,
     pub map_strategy_factory: Box<dyn MapStrategyFactory>,
+    pub replacement_policy_factory: Box<dyn ReplacementPolicyFactory>,
     pub split_i_d: bool,
+    /// Time each access to the first level takes.
+    pub latency: Duration,
+    /// Entries of the victim cache of the first level, if it has one.
+    pub victim_cache: Option<usize>,
+    pub prefetcher_factory: Option<Box<dyn PrefetcherFactory>>,
+    /// Whether to build the reuse histograms of the trace.
+    pub reuse_histograms: bool,
+    /// Levels below the first one, from the closest to the farthest.
+    pub lower_levels: Vec<LevelConfig>,
+    /// Bits of the physical addresses, which the addresses of the trace have
+    /// to fit in and the tags are counted with.
+    pub address_width: Option<u32>,
+    /// Trace of every core in a multi-core run, which gives each core a
+    /// private cache kept coherent through a bus.
+    pub core_traces: Vec<PathBuf>,
+    /// Coherence protocols the multi-core run is simulated with, once each.
+    pub coherence_protocols: Vec<Rc<dyn CoherenceProtocol>>,
+    /// Format of the directory that keeps the cores coherent, or snooping
+    /// if there is none.
+    pub directory: Option<SharerFormat>,
+    /// Format of the traces, or the one their extension gives if there is
+    /// none.
+    pub trace_format: Option<TraceFormat>,
     pub file_path: PathBuf,
 }
 
@@ -32,6 +86,11 @@ impl ParsedArgs {
             .transpose()?
             .unwrap_or(DEFAULT_BLOCK_SIZE);
 
+        let word_size = option_value::<usize>(&params, "-ws")?.unwrap_or(WORD_SIZE);
+        if !word_size.is_power_of_two() {
+            return Err("The word size has to be a power of two".into());
+        }
+
         let cache_size = params
             .iter()
             .position(|a| a == "-cs")
@@ -47,6 +106,7 @@ impl ParsedArgs {
         };
 
         let split_i_d = params.iter().any(|x| x == "-split");
+        let reuse_histograms = params.iter().any(|x| x == "-reuse");
 
         let write_miss_policy = if params.iter().any(|x| x == "-wna") {
             WriteMissPolicy::NoWriteAllocate
@@ -65,28 +125,382 @@ impl ParsedArgs {
                 Box::new(DirectMapFactory)
             };
 
+        let policy_name = option_value::<String>(&params, "-rp")?.unwrap_or("lru".to_owned());
+        let replacement_policy_factory = parse_replacement_policy(&params, &policy_name)?;
+
+        replacement_policy_factory
+            .check_ways(map_strategy_factory.ways(cache_size / (1 + split_i_d as usize)))?;
+
+        let latency = option_value(&params, "-lat")?
+            .map(Duration::from_nanos)
+            .unwrap_or(HIT_DURATION);
+
+        let victim_cache = option_value(&params, "-vc")?;
+        let prefetcher_factory = option_value::<String>(&params, "-pf")?
+            .map(|name| parse_prefetcher(&params, &name))
+            .transpose()?;
+        if replacement_policy_factory.lookahead().is_some()
+            && (victim_cache.is_some() || prefetcher_factory.is_some())
+        {
+            // OPT only knows the future of the demand accesses
+            return Err("OPT can not be used with -vc or -pf".into());
+        }
+
+        if params.iter().any(|a| a == "-l3") && !params.iter().any(|a| a == "-l2") {
+            return Err("-l3 needs -l2".into());
+        }
+        let lower_levels = ["-l2", "-l3"]
+            .into_iter()
+            .map_while(|flag| option_value::<String>(&params, flag).transpose())
+            .map(|spec| LevelConfig::parse(&params, &spec?))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let block_sizes =
+            std::iter::once(block_size).chain(lower_levels.iter().map(|l| l.block_size));
+        for (level, upper_block_size) in lower_levels.iter().zip(block_sizes) {
+            if matches!(level.inclusion, InclusionPolicy::Exclusive)
+                && level.block_size != upper_block_size
+            {
+                return Err("An exclusive level needs the block size of the level above".into());
+            }
+        }
+
+        let address_width: Option<u32> = option_value(&params, "-aw")?;
+        if address_width.is_some_and(|width| width == 0 || width > MemoryAddress::BITS) {
+            return Err(format!("The address width has to be 1 to {}", MemoryAddress::BITS).into());
+        }
+
+        let core_traces: Vec<PathBuf> = params
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| *a == "-core")
+            .map(|(idx, _)| {
+                params
+                    .get(idx + 1)
+                    .map(PathBuf::from)
+                    .ok_or("Missing value for -core")
+            })
+            .collect::<Result<_, _>>()?;
+
+        let coherence_protocols = option_value::<String>(&params, "-protocol")?
+            .unwrap_or("mesi".to_owned())
+            .split(',')
+            .map(parse_coherence_protocol)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let directory = option_value::<String>(&params, "-dir")?
+            .map(|format| parse_sharer_format(&format))
+            .transpose()?;
+
+        let trace_format = option_value::<String>(&params, "-format")?
+            .map(|format| format.parse::<TraceFormat>())
+            .transpose()?;
+
         let file_path = PathBuf::from(params.last().unwrap().clone());
 
         Ok(Self {
             block_size,
+            word_size,
             cache_size,
             write_policy,
             write_miss_policy,
             split_i_d,
             map_strategy_factory,
+            replacement_policy_factory,
+            latency,
+            victim_cache,
+            prefetcher_factory,
+            reuse_histograms,
+            lower_levels,
+            address_width,
+            core_traces,
+            coherence_protocols,
+            directory,
+            trace_format,
             file_path,
         })
     }
 }
 
+impl Default for ParsedArgs {
+    fn default() -> Self {
+        Self {
+            block_size: DEFAULT_BLOCK_SIZE,
+            word_size: WORD_SIZE,
+            cache_size: DEFAULT_CACHE_SIZE,
+            write_policy: WritePolicy::default(),
+            write_miss_policy: WriteMissPolicy::default(),
+            map_strategy_factory: Box::new(DirectMapFactory),
+            replacement_policy_factory: Box::new(LruFactory),
+            split_i_d: false,
+            latency: HIT_DURATION,
+            victim_cache: None,
+            prefetcher_factory: None,
+            reuse_histograms: false,
+            lower_levels: Vec::new(),
+            address_width: None,
+            core_traces: Vec::new(),
+            coherence_protocols: vec![Rc::new(Mesi)],
+            directory: None,
+            trace_format: None,
+            file_path: PathBuf::new(),
+        }
+    }
+}
+
+/// Configuration of a cache level below the first one.
+#[derive(Debug)]
+pub struct LevelConfig {
+    pub block_size: usize,
+    pub cache_size: usize,
+    pub write_policy: WritePolicy,
+    pub write_miss_policy: WriteMissPolicy,
+    pub map_strategy_factory: Box<dyn MapStrategyFactory>,
+    pub replacement_policy_factory: Box<dyn ReplacementPolicyFactory>,
+    pub latency: Duration,
+    pub inclusion: InclusionPolicy,
+    pub victim_cache: Option<usize>,
+    pub prefetcher_factory: Option<Box<dyn PrefetcherFactory>>,
+}
+
+impl LevelConfig {
+    /// Parses a level written as comma separated settings, like
+    /// `cs=1024,bs=16,sa=4,rp=lru,wt,wna,lat=20,inc=inclusive`.
+    ///
+    /// The settings mean the same as the flags of the first level, and the
+    /// ones that are missing take their default value. The flags in `params`
+    /// tune the replacement policy.
+    pub fn parse(params: &[String], spec: &str) -> Result<LevelConfig, Box<dyn Error>> {
+        let mut level = LevelConfig {
+            block_size: DEFAULT_BLOCK_SIZE,
+            cache_size: DEFAULT_CACHE_SIZE,
+            write_policy: WritePolicy::WriteBack,
+            write_miss_policy: WriteMissPolicy::WriteAllocate,
+            map_strategy_factory: Box::new(DirectMapFactory),
+            replacement_policy_factory: Box::new(LruFactory),
+            latency: DEFAULT_LOWER_LEVEL_LATENCY,
+            inclusion: InclusionPolicy::default(),
+            victim_cache: None,
+            prefetcher_factory: None,
+        };
+
+        for setting in spec.split(',') {
+            let (key, value) = setting.split_once('=').unwrap_or((setting, ""));
+            match key {
+                "bs" => level.block_size = value.parse()?,
+                "cs" => level.cache_size = value.parse()?,
+                "wt" => level.write_policy = WritePolicy::WriteThrough,
+                "wna" => level.write_miss_policy = WriteMissPolicy::NoWriteAllocate,
+                "fa" => level.map_strategy_factory = Box::new(FullyAssociativeFactory),
+                "sa" => {
+                    level.map_strategy_factory = Box::new(SetAssociativeFactory {
+                        sets: value.parse()?,
+                    })
+                }
+                "rp" if value.eq_ignore_ascii_case("opt") => {
+                    return Err("OPT can only be used in the first level".into())
+                }
+                "rp" => level.replacement_policy_factory = parse_replacement_policy(params, value)?,
+                "vc" => level.victim_cache = Some(value.parse()?),
+                "pf" => level.prefetcher_factory = Some(parse_prefetcher(params, value)?),
+                "lat" => level.latency = Duration::from_nanos(value.parse()?),
+                "inc" => {
+                    level.inclusion = match value.to_lowercase().as_str() {
+                        "inclusive" => InclusionPolicy::Inclusive,
+                        "exclusive" => InclusionPolicy::Exclusive,
+                        "nine" => InclusionPolicy::NonInclusive,
+                        other => return Err(format!("Invalid inclusion policy: {}", other).into()),
+                    }
+                }
+                _ => return Err(format!("Invalid level setting: {}", setting).into()),
+            }
+        }
+
+        level
+            .replacement_policy_factory
+            .check_ways(level.map_strategy_factory.ways(level.cache_size))?;
+        Ok(level)
+    }
+}
+
+/// Returns the value that follows `flag`, if the flag was given.
+fn option_value<T>(params: &[String], flag: &str) -> Result<Option<T>, Box<dyn Error>>
+where
+    T: FromStr,
+    T::Err: Error + 'static,
+{
+    params
+        .iter()
+        .position(|a| a == flag)
+        .map(|idx| {
+            params
+                .get(idx + 1)
+                .ok_or(format!("Missing value for {}", flag))
+        })
+        .transpose()?
+        .map(|raw| raw.parse::<T>())
+        .transpose()
+        .map_err(Into::into)
+}
+
+/// Builds the replacement policy called `name`, tuned by the flags in
+/// `params`.
+fn parse_replacement_policy(
+    params: &[String],
+    name: &str,
+) -> Result<Box<dyn ReplacementPolicyFactory>, Box<dyn Error>> {
+    let seed = option_value(params, "-seed")?.unwrap_or(DEFAULT_SEED);
+    let rrpv_bits = option_value(params, "-rrpv-bits")?.unwrap_or(DEFAULT_RRPV_BITS);
+    if !(1..=8).contains(&rrpv_bits) {
+        return Err(format!("The RRPV width has to be 1 to 8 bits, got {}", rrpv_bits).into());
+    }
+    let insertion_probability =
+        option_value(params, "-brrip-prob")?.unwrap_or(DEFAULT_BRRIP_PROBABILITY);
+    if !(0.0..=1.0).contains(&insertion_probability) {
+        return Err(format!(
+            "The BRRIP probability has to be 0 to 1, got {}",
+            insertion_probability
+        )
+        .into());
+    }
+    let epsilon = option_value(params, "-bip-epsilon")?.unwrap_or(DEFAULT_BIP_EPSILON);
+    if !(0.0..=1.0).contains(&epsilon) {
+        return Err(format!("The BIP epsilon has to be 0 to 1, got {}", epsilon).into());
+    }
+    let aging_period = option_value(params, "-lfu-aging")?.unwrap_or(0);
+
+    let replacement_policy_factory: Box<dyn ReplacementPolicyFactory> =
+        match name.to_lowercase().as_str() {
+            "lru" => Box::new(LruFactory),
+            "lip" => Box::new(LipFactory),
+            "bip" => Box::new(BipFactory { epsilon, seed }),
+            "dip" => Box::new(DipFactory { epsilon, seed }),
+            "fifo" => Box::new(FifoFactory),
+            "random" => Box::new(RandomFactory { seed }),
+            "clock" | "nru" => Box::new(ClockFactory),
+            "plru" | "tree-plru" => Box::new(TreePlruFactory),
+            "bit-plru" => Box::new(BitPlruFactory),
+            "lfu" => Box::new(LfuFactory { aging_period }),
+            "lfru" => Box::new(LfruFactory),
+            "arc" => Box::new(ArcFactory),
+            "opt" => Box::new(OptFactory::default()),
+            "srrip" => Box::new(SrripFactory { rrpv_bits }),
+            "brrip" => Box::new(BrripFactory {
+                rrpv_bits,
+                insertion_probability,
+                seed,
+            }),
+            "drrip" => Box::new(DrripFactory {
+                rrpv_bits,
+                insertion_probability,
+                seed,
+            }),
+            other => return Err(format!("Invalid replacement policy: {}", other).into()),
+        };
+
+    Ok(replacement_policy_factory)
+}
+
+/// Builds the prefetcher called `name`, tuned by the flags in `params`.
+fn parse_prefetcher(
+    params: &[String],
+    name: &str,
+) -> Result<Box<dyn PrefetcherFactory>, Box<dyn Error>> {
+    let degree = option_value(params, "-pf-degree")?.unwrap_or(DEFAULT_PREFETCH_DEGREE);
+    let streams = option_value(params, "-pf-streams")?.unwrap_or(DEFAULT_PREFETCH_STREAMS);
+
+    let prefetcher_factory: Box<dyn PrefetcherFactory> = match name.to_lowercase().as_str() {
+        "next-line" => Box::new(NextLineFactory { degree }),
+        "stride" => Box::new(StrideFactory { degree }),
+        "stream" => Box::new(StreamFactory {
+            streams,
+            depth: degree,
+        }),
+        other => return Err(format!("Invalid prefetcher: {}", other).into()),
+    };
+
+    Ok(prefetcher_factory)
+}
+
+/// Returns the coherence protocol called `name`.
+fn parse_coherence_protocol(name: &str) -> Result<Rc<dyn CoherenceProtocol>, Box<dyn Error>> {
+    let protocol: Rc<dyn CoherenceProtocol> = match name.to_lowercase().as_str() {
+        "msi" => Rc::new(Msi),
+        "mesi" => Rc::new(Mesi),
+        "moesi" => Rc::new(Moesi),
+        other => return Err(format!("Invalid coherence protocol: {}", other).into()),
+    };
+
+    Ok(protocol)
+}
+
+/// Parses the format of a directory, which is `full` for a full bit vector,
+/// or the number of pointers followed by `b` (Dir_i B) or `nb` (Dir_i NB).
+fn parse_sharer_format(format: &str) -> Result<SharerFormat, Box<dyn Error>> {
+    let format = format.to_lowercase();
+    if format == "full" {
+        return Ok(SharerFormat::FullBitVector);
+    }
+
+    let sharer_format = if let Some(pointers) = format.strip_suffix("nb") {
+        SharerFormat::NoBroadcast {
+            pointers: pointers.parse()?,
+        }
+    } else if let Some(pointers) = format.strip_suffix('b') {
+        SharerFormat::Broadcast {
+            pointers: pointers.parse()?,
+        }
+    } else {
+        return Err(format!("Invalid directory format: {}", format).into());
+    };
+
+    match sharer_format {
+        SharerFormat::Broadcast { pointers: 0 } | SharerFormat::NoBroadcast { pointers: 0 } => {
+            Err("A directory needs at least one pointer".into())
+        }
+        sharer_format => Ok(sharer_format),
+    }
+}
+
 impl Display for ParsedArgs {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Block Size: {}", self.block_size)?;
+        writeln!(f, "Word Size: {}", self.word_size)?;
         writeln!(f, "Cache Size: {}", self.cache_size)?;
         writeln!(f, "Write Policy: {:?}", self.write_policy)?;
         writeln!(f, "Write Miss Policy: {:?}", self.write_miss_policy)?;
         writeln!(f, "Map Strategy: {:?}", self.map_strategy_factory)?;
+        writeln!(
+            f,
+            "Replacement Policy: {:?}",
+            self.replacement_policy_factory
+        )?;
         writeln!(f, "Split I/D: {}", self.split_i_d)?;
+        writeln!(f, "Latency: {:?}", self.latency)?;
+        if let Some(entries) = self.victim_cache {
+            writeln!(f, "Victim Cache Entries: {}", entries)?;
+        }
+        if let Some(ref prefetcher_factory) = self.prefetcher_factory {
+            writeln!(f, "Prefetcher: {:?}", prefetcher_factory)?;
+        }
+        for (idx, level) in self.lower_levels.iter().enumerate() {
+            writeln!(f, "L{}: {:?}", idx + 2, level)?;
+        }
+        if let Some(address_width) = self.address_width {
+            writeln!(f, "Address Width: {}", address_width)?;
+        }
+        for (core, trace) in self.core_traces.iter().enumerate() {
+            writeln!(f, "Core {}: {:?}", core, trace)?;
+        }
+        if !self.core_traces.is_empty() {
+            writeln!(f, "Coherence Protocols: {:?}", self.coherence_protocols)?;
+            if let Some(directory) = self.directory {
+                writeln!(f, "Directory: {:?}", directory)?;
+            }
+        }
+        if let Some(trace_format) = self.trace_format {
+            writeln!(f, "Trace Format: {}", trace_format)?;
+        }
         writeln!(f, "File Path: {:?}", self.file_path)
     }
 }
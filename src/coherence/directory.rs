@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::ops::Add;
+
+use crate::coherence::BusTransaction;
+use crate::{MemoryAddress, WORD_SIZE};
+
+/// How a directory entry records the caches that hold a block.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SharerFormat {
+    /// One bit per core, so every sharer is known.
+    FullBitVector,
+    /// Up to `pointers` core ids. Once they overflow the sharers are unknown,
+    /// and a write invalidates the block in every core (Dir_i B).
+    Broadcast { pointers: usize },
+    /// Up to `pointers` core ids. A new sharer that does not fit invalidates
+    /// the block in one of the others (Dir_i NB).
+    NoBroadcast { pointers: usize },
+}
+
+/// Statistics of a directory.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct DirectoryStats {
+    /// Requests that read an entry of the directory.
+    pub lookups: u128,
+    /// Point-to-point messages between the caches and the directory.
+    pub messages: u128,
+    /// Blocks invalidated because their entry had no room for another
+    /// sharer.
+    pub evictions: u128,
+}
+
+impl Add for DirectoryStats {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self {
+            lookups: self.lookups + other.lookups,
+            messages: self.messages + other.messages,
+            evictions: self.evictions + other.evictions,
+        }
+    }
+}
+
+/// Directory entry of a block.
+#[derive(Default)]
+struct Entry {
+    /// Cores that hold the block, from the oldest to the newest.
+    sharers: Vec<usize>,
+    /// Whether more cores than the pointers can hold have the block.
+    overflow: bool,
+    /// Core that holds the block in a state that has to answer reads.
+    owner: Option<usize>,
+}
+
+/// Directory that records which cores hold every block, so a request is
+/// only sent to them instead of to every core.
+///
+/// The caches tell it about the blocks they evict, so its sharers are exact
+/// unless they overflow.
+pub struct Directory {
+    format: SharerFormat,
+    block_bytes: MemoryAddress,
+    entries: HashMap<MemoryAddress, Entry>,
+    pub stats: DirectoryStats,
+}
+
+impl Directory {
+    pub fn new(format: SharerFormat, block_size: usize) -> Self {
+        Self {
+            format,
+            block_bytes: (block_size * WORD_SIZE) as MemoryAddress,
+            entries: HashMap::new(),
+            stats: DirectoryStats::default(),
+        }
+    }
+
+    /// Looks up the block at `address` for a `transaction` of `core`.
+    ///
+    /// Returns the cores the request is forwarded to, out of `cores`, and
+    /// whether any other core holds the block.
+    pub fn lookup(
+        &mut self,
+        core: usize,
+        transaction: BusTransaction,
+        address: MemoryAddress,
+        cores: usize,
+    ) -> (Vec<usize>, bool) {
+        // The request from the core
+        self.stats.lookups += 1;
+        self.stats.messages += 1;
+
+        let Some(entry) = self.entries.get(&(address / self.block_bytes)) else {
+            self.stats.messages += 1;
+            return (Vec::new(), false);
+        };
+
+        let shared = entry.overflow || entry.sharers.iter().any(|&c| c != core);
+        let targets: Vec<usize> = match transaction {
+            BusTransaction::Read => entry.owner.into_iter().filter(|&c| c != core).collect(),
+            _ if entry.overflow => (0..cores).filter(|&c| c != core).collect(),
+            _ => entry
+                .sharers
+                .iter()
+                .copied()
+                .filter(|&c| c != core)
+                .collect(),
+        };
+
+        // A forward and an answer for every target, and the reply to the core
+        self.stats.messages += 2 * targets.len() as u128 + 1;
+        (targets, shared)
+    }
+
+    /// Records that `core` holds the block at `address` after its
+    /// `transaction`, as the owner if `owns` is set. `kept_owner` tells
+    /// whether the previous owner still answers reads.
+    ///
+    /// Returns the cores that have to drop the block because the entry has
+    /// no room for them.
+    pub fn update(
+        &mut self,
+        core: usize,
+        transaction: BusTransaction,
+        address: MemoryAddress,
+        owns: bool,
+        kept_owner: bool,
+    ) -> Vec<usize> {
+        let entry = self.entries.entry(address / self.block_bytes).or_default();
+
+        if transaction != BusTransaction::Read {
+            // Writes without a copy, like the ones that do not allocate,
+            // leave no sharers
+            let holds = entry.overflow || entry.sharers.contains(&core);
+            *entry = Entry::default();
+            if transaction == BusTransaction::ReadExclusive || holds {
+                entry.sharers.push(core);
+                entry.owner = Some(core);
+            }
+            return Vec::new();
+        }
+
+        if !kept_owner {
+            entry.owner = None;
+        }
+        if owns {
+            entry.owner = Some(core);
+        }
+        if entry.sharers.contains(&core) || entry.overflow {
+            return Vec::new();
+        }
+
+        let pointers = match self.format {
+            SharerFormat::FullBitVector => usize::MAX,
+            SharerFormat::Broadcast { pointers } | SharerFormat::NoBroadcast { pointers } => {
+                pointers
+            }
+        };
+        let mut evicted = Vec::new();
+        if entry.sharers.len() >= pointers {
+            match self.format {
+                SharerFormat::NoBroadcast { .. } => {
+                    // Keep the owner, as it is the one that answers reads
+                    let victim = entry
+                        .sharers
+                        .iter()
+                        .position(|&c| Some(c) != entry.owner)
+                        .unwrap_or(0);
+                    let victim = entry.sharers.remove(victim);
+                    if entry.owner == Some(victim) {
+                        entry.owner = None;
+                    }
+                    evicted.push(victim);
+                }
+                _ => {
+                    entry.overflow = true;
+                    return Vec::new();
+                }
+            }
+        }
+        entry.sharers.push(core);
+
+        // An invalidation and its answer for every evicted sharer
+        self.stats.evictions += evicted.len() as u128;
+        self.stats.messages += 2 * evicted.len() as u128;
+        evicted
+    }
+
+    /// Records that `core` evicted the block at `address`.
+    pub fn evicted(&mut self, core: usize, address: MemoryAddress) {
+        self.stats.messages += 1;
+
+        let block = address / self.block_bytes;
+        let Some(entry) = self.entries.get_mut(&block) else {
+            return;
+        };
+        entry.sharers.retain(|&c| c != core);
+        if entry.owner == Some(core) {
+            entry.owner = None;
+        }
+        if entry.sharers.is_empty() && !entry.overflow {
+            self.entries.remove(&block);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use crate::coherence::{BusTransaction, CoherenceProtocol, CoherenceState, SnoopAction};
+
+/// MESI (Illinois) protocol. A block read by a single cache is exclusive, so
+/// writing it later does not use the bus. Only modified blocks are sent from
+/// cache to cache, and they are written back to memory at the same time.
+#[derive(Debug)]
+pub struct Mesi;
+
+impl CoherenceProtocol for Mesi {
+    fn name(&self) -> &'static str {
+        "MESI"
+    }
+
+    fn read_miss(&self, shared: bool) -> CoherenceState {
+        if shared {
+            CoherenceState::Shared
+        } else {
+            CoherenceState::Exclusive
+        }
+    }
+
+    fn snoop(&self, state: CoherenceState, transaction: BusTransaction) -> SnoopAction {
+        use BusTransaction::*;
+        use CoherenceState::*;
+
+        let modified = state == Modified;
+        let next_state = match (state, transaction) {
+            (Invalid, _) => Invalid,
+            (_, Read) => Shared,
+            (_, ReadExclusive | Upgrade) => Invalid,
+        };
+
+        SnoopAction {
+            next_state,
+            flush: modified,
+            supply: modified,
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+use crate::cache::Cache;
+use crate::coherence::directory::{Directory, DirectoryStats, SharerFormat};
+use crate::coherence::sharing::{FalselyShared, Sharing, SharingTracker};
+use crate::MemoryAddress;
+
+pub mod directory;
+pub mod mesi;
+pub mod moesi;
+pub mod msi;
+pub mod sharing;
+
+/// State of a block in a cache that is kept coherent with other caches.
+#[derive(Clone, Copy, Default, PartialOrd, PartialEq, Debug)]
+pub enum CoherenceState {
+    /// The only copy, and it is newer than memory.
+    Modified,
+    /// A copy newer than memory that other caches may share. Its cache
+    /// writes it back when evicting it.
+    Owned,
+    /// The only copy, and it matches memory.
+    Exclusive,
+    /// One of several copies. It matches memory unless another cache owns
+    /// the block.
+    Shared,
+    #[default]
+    Invalid,
+}
+
+/// Request a cache sends to the others through the bus.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BusTransaction {
+    /// Read miss (BusRd).
+    Read,
+    /// Write miss, which reads the block and invalidates the other copies
+    /// (BusRdX).
+    ReadExclusive,
+    /// Write to a shared block, which only invalidates the other copies
+    /// (BusUpgr).
+    Upgrade,
+}
+
+/// What a cache does with its copy of a block when it sees a transaction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SnoopAction {
+    pub next_state: CoherenceState,
+    /// Whether the block is written back to memory.
+    pub flush: bool,
+    /// Whether the cache sends the block to the requester instead of memory.
+    pub supply: bool,
+}
+
+/// Combined reply of the caches that saw a transaction.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct SnoopReply {
+    /// Whether another cache had a copy of the block.
+    pub shared: bool,
+    /// Whether another cache sent the block.
+    pub supplied: bool,
+    /// Whether a cache that saw the transaction still has to answer the
+    /// reads of the block.
+    pub owned: bool,
+}
+
+/// Defines the state changes of a snooping coherence protocol.
+///
+/// A write miss always sends [`BusTransaction::ReadExclusive`] and leaves
+/// the block modified, so protocols only decide the rest.
+pub trait CoherenceProtocol: fmt::Debug {
+    /// Name of the protocol shown in the results.
+    fn name(&self) -> &'static str;
+
+    /// Returns the state of a block brought by a read miss, given whether
+    /// another cache had a copy.
+    fn read_miss(&self, shared: bool) -> CoherenceState;
+
+    /// Returns the transaction a write hit on a block in `state` needs, if
+    /// any. The block is modified after it.
+    fn write_hit(&self, state: CoherenceState) -> Option<BusTransaction> {
+        match state {
+            CoherenceState::Shared | CoherenceState::Owned => Some(BusTransaction::Upgrade),
+            _ => None,
+        }
+    }
+
+    /// Returns what a cache with a block in `state` does when it sees
+    /// `transaction` for it.
+    fn snoop(&self, state: CoherenceState, transaction: BusTransaction) -> SnoopAction;
+}
+
+/// Interconnect shared by the private caches of several cores. Without a
+/// directory every cache snoops the transactions of the others, and with one
+/// they only reach the caches that hold the block.
+pub struct Bus {
+    pub protocol: Rc<dyn CoherenceProtocol>,
+    directory: Option<RefCell<Directory>>,
+    sharing: RefCell<SharingTracker>,
+    caches: Vec<Weak<RefCell<Cache>>>,
+}
+
+impl Bus {
+    pub fn new(protocol: Rc<dyn CoherenceProtocol>) -> Self {
+        Self {
+            protocol,
+            directory: None,
+            sharing: RefCell::default(),
+            caches: Vec::new(),
+        }
+    }
+
+    /// Creates an interconnect that keeps the caches coherent through a
+    /// directory, which records the sharers of the blocks of `block_size`
+    /// words in `format`.
+    pub fn with_directory(
+        protocol: Rc<dyn CoherenceProtocol>,
+        format: SharerFormat,
+        block_size: usize,
+    ) -> Self {
+        Self {
+            directory: Some(RefCell::new(Directory::new(format, block_size))),
+            ..Self::new(protocol)
+        }
+    }
+
+    /// Returns the statistics of the directory, if there is one.
+    pub fn directory_stats(&self) -> Option<DirectoryStats> {
+        self.directory
+            .as_ref()
+            .map(|directory| directory.borrow().stats)
+    }
+
+    /// Connects `cache` to `bus`, so it sends its misses and writes to shared
+    /// blocks through it and sees the ones of the other caches.
+    pub fn connect(bus: &Rc<RefCell<Bus>>, cache: &Rc<RefCell<Cache>>) {
+        let core = {
+            let mut bus = bus.borrow_mut();
+            bus.caches.push(Rc::downgrade(cache));
+            bus.caches.len() - 1
+        };
+        cache.borrow_mut().set_bus(core, Rc::clone(bus));
+    }
+
+    /// Sends `transaction` for the block at `address` to the caches that
+    /// need it, which are all except the one of `core` when snooping.
+    pub fn broadcast(
+        &self,
+        core: usize,
+        transaction: BusTransaction,
+        address: MemoryAddress,
+    ) -> SnoopReply {
+        let Some(ref directory) = self.directory else {
+            let others = (0..self.caches.len()).filter(|&other| other != core);
+            return self.send(others, transaction, address);
+        };
+
+        let (targets, shared) =
+            directory
+                .borrow_mut()
+                .lookup(core, transaction, address, self.caches.len());
+        let mut reply = self.send(targets, transaction, address);
+        reply.shared |= shared;
+
+        let owns = match transaction {
+            BusTransaction::Read => self.protocol.read_miss(reply.shared) != CoherenceState::Shared,
+            _ => true,
+        };
+        let evicted = directory
+            .borrow_mut()
+            .update(core, transaction, address, owns, reply.owned);
+        for other in evicted {
+            if let Some(cache) = self.caches[other].upgrade() {
+                cache.borrow_mut().recall(address);
+            }
+        }
+
+        reply
+    }
+
+    /// Returns the time of the last write, see [`SharingTracker::now`].
+    pub fn now(&self) -> u128 {
+        self.sharing.borrow().now()
+    }
+
+    /// Records a write of `core` to `address`.
+    pub fn record_write(&self, core: usize, address: MemoryAddress) {
+        self.sharing.borrow_mut().write(core, address);
+    }
+
+    /// Classifies a coherence miss, see [`SharingTracker::classify`].
+    pub fn classify_sharing(
+        &self,
+        core: usize,
+        address: MemoryAddress,
+        block_bytes: MemoryAddress,
+        since: u128,
+    ) -> Option<Sharing> {
+        self.sharing
+            .borrow_mut()
+            .classify(core, address, block_bytes, since)
+    }
+
+    /// Returns the `count` blocks with the most false sharing misses.
+    pub fn most_falsely_shared(&self, count: usize) -> Vec<FalselyShared> {
+        self.sharing.borrow().most_falsely_shared(count)
+    }
+
+    /// Records that the cache of `core` evicted the block at `address`.
+    pub fn evicted(&self, core: usize, address: MemoryAddress) {
+        if let Some(ref directory) = self.directory {
+            directory.borrow_mut().evicted(core, address);
+        }
+    }
+
+    /// Sends `transaction` to the caches of `cores`, and combines their
+    /// replies.
+    fn send(
+        &self,
+        cores: impl IntoIterator<Item = usize>,
+        transaction: BusTransaction,
+        address: MemoryAddress,
+    ) -> SnoopReply {
+        let mut reply = SnoopReply::default();
+
+        for other in cores {
+            let Some(cache) = self.caches[other].upgrade() else {
+                continue;
+            };
+
+            let other_reply = cache.borrow_mut().snoop(transaction, address);
+            reply.shared |= other_reply.shared;
+            reply.supplied |= other_reply.supplied;
+            reply.owned |= other_reply.owned;
+        }
+
+        reply
+    }
+}
@@ -0,0 +1,42 @@
+use crate::coherence::{BusTransaction, CoherenceProtocol, CoherenceState, SnoopAction};
+
+/// MOESI protocol. A modified block that another cache reads becomes owned
+/// instead of being written back, so its cache keeps supplying it and memory
+/// is only written when the owner evicts it.
+#[derive(Debug)]
+pub struct Moesi;
+
+impl CoherenceProtocol for Moesi {
+    fn name(&self) -> &'static str {
+        "MOESI"
+    }
+
+    fn read_miss(&self, shared: bool) -> CoherenceState {
+        if shared {
+            CoherenceState::Shared
+        } else {
+            CoherenceState::Exclusive
+        }
+    }
+
+    fn snoop(&self, state: CoherenceState, transaction: BusTransaction) -> SnoopAction {
+        use BusTransaction::*;
+        use CoherenceState::*;
+
+        let owner = matches!(state, Modified | Owned);
+        let next_state = match (state, transaction) {
+            (Invalid, _) => Invalid,
+            (Modified | Owned, Read) => Owned,
+            (_, Read) => Shared,
+            (_, ReadExclusive | Upgrade) => Invalid,
+        };
+
+        // The requester of a write takes over the dirty data, so the owner
+        // never writes it back
+        SnoopAction {
+            next_state,
+            flush: false,
+            supply: owner && transaction != Upgrade,
+        }
+    }
+}
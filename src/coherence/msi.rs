@@ -0,0 +1,34 @@
+use crate::coherence::{BusTransaction, CoherenceProtocol, CoherenceState, SnoopAction};
+
+/// MSI protocol. Every read miss leaves the block shared, so writing a block
+/// that no other cache holds still needs an upgrade.
+#[derive(Debug)]
+pub struct Msi;
+
+impl CoherenceProtocol for Msi {
+    fn name(&self) -> &'static str {
+        "MSI"
+    }
+
+    fn read_miss(&self, _shared: bool) -> CoherenceState {
+        CoherenceState::Shared
+    }
+
+    fn snoop(&self, state: CoherenceState, transaction: BusTransaction) -> SnoopAction {
+        use BusTransaction::*;
+        use CoherenceState::*;
+
+        let modified = state == Modified;
+        let next_state = match (state, transaction) {
+            (Invalid, _) => Invalid,
+            (_, Read) => Shared,
+            (_, ReadExclusive | Upgrade) => Invalid,
+        };
+
+        SnoopAction {
+            next_state,
+            flush: modified,
+            supply: modified,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{MemoryAddress, WORD_SIZE};
+
+/// Cause of a coherence miss.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sharing {
+    /// Another core wrote the word the miss accesses.
+    True,
+    /// Other cores only wrote other words of the block.
+    False,
+}
+
+/// A block whose coherence misses came from false sharing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FalselyShared {
+    pub address: MemoryAddress,
+    pub misses: u128,
+    /// Cores that missed on the block or wrote the words that invalidated
+    /// it.
+    pub cores: BTreeSet<usize>,
+}
+
+/// Tracks the last write to every word, to tell true from false sharing.
+#[derive(Default)]
+pub struct SharingTracker {
+    /// Writes seen so far, used as the clock.
+    writes: u128,
+    /// Core that last wrote every word, and when.
+    last_writes: HashMap<MemoryAddress, (usize, u128)>,
+    falsely_shared: HashMap<MemoryAddress, FalselyShared>,
+}
+
+impl SharingTracker {
+    /// Returns the current time, to compare it with the time of the writes
+    /// that come later.
+    pub fn now(&self) -> u128 {
+        self.writes
+    }
+
+    /// Records a write of `core` to the word at `address`.
+    pub fn write(&mut self, core: usize, address: MemoryAddress) {
+        self.writes += 1;
+        let word = address - address % WORD_SIZE as MemoryAddress;
+        self.last_writes.insert(word, (core, self.writes));
+    }
+
+    /// Classifies the coherence miss of `core` on the word at `address`,
+    /// whose block of `block_bytes` bytes was invalidated at `since`.
+    ///
+    /// Returns `None` if no other core wrote the block since then, as
+    /// happens when the directory evicted it.
+    pub fn classify(
+        &mut self,
+        core: usize,
+        address: MemoryAddress,
+        block_bytes: MemoryAddress,
+        since: u128,
+    ) -> Option<Sharing> {
+        let word_bytes = WORD_SIZE as MemoryAddress;
+        let block_address = address - address % block_bytes;
+        let word = address - address % word_bytes;
+
+        let mut writers = BTreeSet::new();
+        for other_word in (block_address..block_address + block_bytes).step_by(WORD_SIZE) {
+            match self.last_writes.get(&other_word) {
+                Some(&(writer, time)) if writer != core && time > since => {
+                    if other_word == word {
+                        return Some(Sharing::True);
+                    }
+                    writers.insert(writer);
+                }
+                _ => {}
+            }
+        }
+        if writers.is_empty() {
+            return None;
+        }
+
+        let falsely_shared =
+            self.falsely_shared
+                .entry(block_address)
+                .or_insert_with(|| FalselyShared {
+                    address: block_address,
+                    misses: 0,
+                    cores: BTreeSet::new(),
+                });
+        falsely_shared.misses += 1;
+        falsely_shared.cores.insert(core);
+        falsely_shared.cores.extend(writers);
+        Some(Sharing::False)
+    }
+
+    /// Returns the `count` blocks with the most false sharing misses.
+    pub fn most_falsely_shared(&self, count: usize) -> Vec<FalselyShared> {
+        let mut blocks: Vec<_> = self.falsely_shared.values().cloned().collect();
+        blocks.sort_by(|a, b| b.misses.cmp(&a.misses).then(a.address.cmp(&b.address)));
+        blocks.truncate(count);
+        blocks
+    }
+}
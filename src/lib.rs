@@ -1,16 +1,32 @@
 use std::time::Duration;
 
+pub mod analysis;
+pub mod binary_trace;
 pub mod cache;
 pub mod cache_block;
 pub mod cli_parser;
+pub mod coherence;
 pub mod logger;
-pub mod lru;
 pub mod map_strategies;
+pub mod miss_classifier;
+pub mod multi_core_simulator;
+pub mod prefetchers;
+pub mod replacement_policies;
+pub mod rng;
+pub mod trace_reader;
 pub mod trace_simulator;
+pub mod victim_cache;
 
 pub const HIT_DURATION: Duration = Duration::from_nanos(5);
 pub const MISS_DURATION: Duration = Duration::from_nanos(100);
+pub const DEFAULT_LOWER_LEVEL_LATENCY: Duration = Duration::from_nanos(20);
 pub const WORD_SIZE: usize = 4;
 pub const DEFAULT_BLOCK_SIZE: usize = 64;
 pub const DEFAULT_CACHE_SIZE: usize = 256;
-pub type MemoryAddress = u32;
+pub const DEFAULT_SEED: u64 = 0;
+pub const DEFAULT_RRPV_BITS: u32 = 2;
+pub const DEFAULT_BRRIP_PROBABILITY: f64 = 1.0 / 32.0;
+pub const DEFAULT_BIP_EPSILON: f64 = 1.0 / 32.0;
+pub const DEFAULT_PREFETCH_DEGREE: usize = 2;
+pub const DEFAULT_PREFETCH_STREAMS: usize = 4;
+pub type MemoryAddress = u64;
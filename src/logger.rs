@@ -1,21 +1,156 @@
+use crate::analysis::reuse::ReuseHistograms;
 use crate::cache::{
     AccessType::{self, *},
     ValueType::*,
 };
-use crate::{HIT_DURATION, MISS_DURATION};
+use crate::coherence::directory::DirectoryStats;
+use crate::coherence::sharing::{FalselyShared, Sharing};
+use crate::coherence::BusTransaction;
+use crate::miss_classifier::MissKind;
+use crate::MISS_DURATION;
 use std::fmt::{Display, Formatter};
 use std::ops::Add;
 use std::time::Duration;
 
+/// Misses of an access type split by their cause.
+#[derive(Default, Clone, Copy)]
+pub struct MissKinds {
+    pub compulsory: u128,
+    pub capacity: u128,
+    pub conflict: u128,
+}
+
+impl MissKinds {
+    fn count(&mut self, kind: MissKind) {
+        match kind {
+            MissKind::Compulsory => self.compulsory += 1,
+            MissKind::Capacity => self.capacity += 1,
+            MissKind::Conflict => self.conflict += 1,
+        }
+    }
+}
+
+impl Add for MissKinds {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self {
+            compulsory: self.compulsory + other.compulsory,
+            capacity: self.capacity + other.capacity,
+            conflict: self.conflict + other.conflict,
+        }
+    }
+}
+
+/// Statistics of a prefetcher.
+#[derive(Default, Clone, Copy)]
+pub struct PrefetchStats {
+    /// Blocks brought by a prefetch.
+    pub issued: u128,
+    /// Prefetched blocks used by a demand access.
+    pub useful: u128,
+    /// Useful prefetches that were still on the way when the access came.
+    pub late: u128,
+    /// Prefetched blocks evicted before being used.
+    pub pollution: u128,
+}
+
+impl Add for PrefetchStats {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self {
+            issued: self.issued + other.issued,
+            useful: self.useful + other.useful,
+            late: self.late + other.late,
+            pollution: self.pollution + other.pollution,
+        }
+    }
+}
+
+/// Statistics of a cache kept coherent with the caches of other cores.
+#[derive(Default, Clone, Copy)]
+pub struct CoherenceStats {
+    pub bus_reads: u128,
+    pub bus_read_exclusives: u128,
+    pub bus_upgrades: u128,
+    /// Blocks invalidated by the transactions of other cores.
+    pub invalidations: u128,
+    /// Blocks sent to other cores instead of memory.
+    pub interventions: u128,
+    /// Misses on blocks that other cores invalidated.
+    pub coherence_misses: u128,
+    /// Coherence misses on words other cores wrote.
+    pub true_sharing_misses: u128,
+    /// Coherence misses on blocks where other cores only wrote other words.
+    pub false_sharing_misses: u128,
+}
+
+impl CoherenceStats {
+    pub fn bus_transactions(&self) -> u128 {
+        self.bus_reads + self.bus_read_exclusives + self.bus_upgrades
+    }
+}
+
+impl Add for CoherenceStats {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self {
+            bus_reads: self.bus_reads + other.bus_reads,
+            bus_read_exclusives: self.bus_read_exclusives + other.bus_read_exclusives,
+            bus_upgrades: self.bus_upgrades + other.bus_upgrades,
+            invalidations: self.invalidations + other.invalidations,
+            interventions: self.interventions + other.interventions,
+            coherence_misses: self.coherence_misses + other.coherence_misses,
+            true_sharing_misses: self.true_sharing_misses + other.true_sharing_misses,
+            false_sharing_misses: self.false_sharing_misses + other.false_sharing_misses,
+        }
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct Logger {
     pub instruction_references: u128,
     pub data_references: u128,
     pub instruction_misses: u128,
     pub data_misses: u128,
+    pub instruction_miss_kinds: MissKinds,
+    pub data_miss_kinds: MissKinds,
     pub memory_reads: u128,
     pub memory_writes: u128,
     pub running_time: Duration,
+    /// Access number at which a set dueling monitor switched policies, and
+    /// the policy it switched to.
+    pub dueling_winners: Vec<(u128, &'static str)>,
+    /// Policy every set used, for set dueling policies in caches with too
+    /// few sets to duel.
+    pub dueling_fallback: Option<&'static str>,
+    /// Misses found in the B1 and B2 ghost lists, for policies that have them.
+    pub ghost_hits: Option<(u128, u128)>,
+    /// Misses served by the victim cache, if the cache has one.
+    pub victim_hits: Option<u128>,
+    /// Statistics of the prefetcher, if the cache has one.
+    pub prefetches: Option<PrefetchStats>,
+    /// Flush records of the trace, if it has any.
+    pub flushes: Option<u128>,
+    /// Bits the tags take, if the address width was given.
+    pub tag_storage: Option<u128>,
+    /// Blocks dropped because an inclusive level below evicted them.
+    pub back_invalidations: u128,
+    /// Distinct words stored across all the levels at the end of the run,
+    /// and the words all the levels can hold.
+    pub effective_capacity: Option<(u128, u128)>,
+    /// Reuse histograms of the trace, if they were asked for.
+    pub reuse_histograms: Option<ReuseHistograms>,
+    /// Coherence statistics, if the cache is connected to a bus.
+    pub coherence: Option<CoherenceStats>,
+    /// Logs of the private caches of every core, in a multi-core run.
+    pub cores: Vec<Logger>,
+    /// Statistics of the directory that keeps the cores coherent, if there
+    /// is one.
+    pub directory: Option<DirectoryStats>,
+    /// Blocks with the most false sharing misses, in a multi-core run.
+    pub falsely_shared: Vec<FalselyShared>,
+    /// Logs of the caches below this one, from the closest to the farthest.
+    pub lower_levels: Vec<Logger>,
 }
 
 impl Logger {
@@ -29,17 +164,20 @@ impl Logger {
         }
     }
 
-    pub fn hit(&mut self) {
-        self.running_time += HIT_DURATION;
+    pub fn hit(&mut self, latency: Duration) {
+        self.running_time += latency;
     }
 
-    pub fn miss(&mut self, access_type: &AccessType) {
+    pub fn miss(&mut self, access_type: &AccessType, kind: MissKind) {
         match access_type {
-            Read(value_type) => match value_type {
-                Instruction => self.instruction_misses += 1,
-                Data => self.data_misses += 1,
-            },
-            Write => self.data_misses += 1,
+            Read(Instruction) => {
+                self.instruction_misses += 1;
+                self.instruction_miss_kinds.count(kind);
+            }
+            Read(Data) | Write => {
+                self.data_misses += 1;
+                self.data_miss_kinds.count(kind);
+            }
         }
     }
 
@@ -56,10 +194,115 @@ impl Logger {
         self.memory_reads += words;
         self.running_time += MISS_DURATION * words as u32;
     }
-}
 
-impl Display for Logger {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    /// Counts words written to a lower cache level. The time they take is
+    /// logged by that level.
+    pub fn next_level_write(&mut self, words: u128) {
+        self.memory_writes += words;
+    }
+
+    /// Counts words read from a lower cache level. The time they take is
+    /// logged by that level.
+    pub fn next_level_read(&mut self, words: u128) {
+        self.memory_reads += words;
+    }
+
+    pub fn victim_hit(&mut self) {
+        *self.victim_hits.get_or_insert(0) += 1;
+    }
+
+    pub fn flush(&mut self) {
+        *self.flushes.get_or_insert(0) += 1;
+    }
+
+    pub fn prefetch_issued(&mut self) {
+        self.prefetches.get_or_insert_with(Default::default).issued += 1;
+    }
+
+    /// Counts a demand access to a prefetched block, which waits `wait` for
+    /// the prefetch to arrive.
+    pub fn useful_prefetch(&mut self, wait: Duration) {
+        let prefetches = self.prefetches.get_or_insert_with(Default::default);
+        prefetches.useful += 1;
+        if !wait.is_zero() {
+            prefetches.late += 1;
+            self.running_time += wait;
+        }
+    }
+
+    pub fn prefetch_pollution(&mut self) {
+        self.prefetches
+            .get_or_insert_with(Default::default)
+            .pollution += 1;
+    }
+
+    pub fn back_invalidation(&mut self) {
+        self.back_invalidations += 1;
+    }
+
+    pub fn bus_transaction(&mut self, transaction: BusTransaction) {
+        let coherence = self.coherence.get_or_insert_with(Default::default);
+        match transaction {
+            BusTransaction::Read => coherence.bus_reads += 1,
+            BusTransaction::ReadExclusive => coherence.bus_read_exclusives += 1,
+            BusTransaction::Upgrade => coherence.bus_upgrades += 1,
+        }
+    }
+
+    /// Counts a block invalidated by the transaction of another core.
+    pub fn coherence_invalidation(&mut self) {
+        self.coherence
+            .get_or_insert_with(Default::default)
+            .invalidations += 1;
+    }
+
+    /// Counts a block sent to another core.
+    pub fn intervention(&mut self) {
+        self.coherence
+            .get_or_insert_with(Default::default)
+            .interventions += 1;
+    }
+
+    /// Counts a miss on a block another core invalidated, with its cause
+    /// if it is known.
+    pub fn coherence_miss(&mut self, sharing: Option<Sharing>) {
+        let coherence = self.coherence.get_or_insert_with(Default::default);
+        coherence.coherence_misses += 1;
+        match sharing {
+            Some(Sharing::True) => coherence.true_sharing_misses += 1,
+            Some(Sharing::False) => coherence.false_sharing_misses += 1,
+            None => {}
+        }
+    }
+
+    /// Returns the time spent in this level and all the levels below it.
+    pub fn total_running_time(&self) -> Duration {
+        self.running_time
+            + self
+                .lower_levels
+                .iter()
+                .map(Logger::total_running_time)
+                .sum::<Duration>()
+    }
+
+    /// Returns the sum of the logs of every core.
+    pub fn cores_total(&self) -> Logger {
+        self.cores.iter().cloned().fold(Logger::default(), Add::add)
+    }
+
+    /// Returns the running time of the core that took the longest, which is
+    /// the time of the whole run as the cores work at the same time.
+    pub fn slowest_core_time(&self) -> Duration {
+        self.cores
+            .iter()
+            .map(|core_log| core_log.running_time)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Writes the table of a single level. The rows that only make sense in
+    /// a hierarchy are written when `hierarchy` is set.
+    fn fmt_level(&self, f: &mut Formatter<'_>, hierarchy: bool) -> std::fmt::Result {
         writeln!(f, "┌──────────────────────────┬────────────────┐")?;
         writeln!(f, "│ Metric                   │ Value          │")?;
         writeln!(f, "├──────────────────────────┼────────────────┤")?;
@@ -78,7 +321,9 @@ impl Display for Logger {
             "│ Instruction Misses       │ {:<14} │",
             self.instruction_misses
         )?;
+        fmt_miss_kinds(f, &self.instruction_miss_kinds)?;
         writeln!(f, "│ Data Misses              │ {:<14} │", self.data_misses)?;
+        fmt_miss_kinds(f, &self.data_miss_kinds)?;
         writeln!(
             f,
             "│ Memory Read Words        │ {:<14} │",
@@ -94,21 +339,260 @@ impl Display for Logger {
             "│ Running Time             │ {:<14?} │",
             self.running_time
         )?;
-        writeln!(f, "└──────────────────────────┴────────────────┘")
+        if let Some(tag_storage) = self.tag_storage {
+            writeln!(f, "│ Tag Storage Bits         │ {:<14} │", tag_storage)?;
+        }
+        if let Some(flushes) = self.flushes {
+            writeln!(f, "│ Flushes                  │ {:<14} │", flushes)?;
+        }
+        if let Some((b1_hits, b2_hits)) = self.ghost_hits {
+            writeln!(f, "│ B1 Ghost Hits            │ {:<14} │", b1_hits)?;
+            writeln!(f, "│ B2 Ghost Hits            │ {:<14} │", b2_hits)?;
+        }
+        if let Some(victim_hits) = self.victim_hits {
+            writeln!(f, "│ Victim Cache Hits        │ {:<14} │", victim_hits)?;
+        }
+        if let Some(prefetches) = self.prefetches {
+            writeln!(
+                f,
+                "│ Prefetches Issued        │ {:<14} │",
+                prefetches.issued
+            )?;
+            writeln!(
+                f,
+                "│ Useful Prefetches        │ {:<14} │",
+                prefetches.useful
+            )?;
+            writeln!(f, "│ Late Prefetches          │ {:<14} │", prefetches.late)?;
+            writeln!(
+                f,
+                "│ Prefetch Pollution       │ {:<14} │",
+                prefetches.pollution
+            )?;
+        }
+        if let Some(coherence) = self.coherence {
+            writeln!(
+                f,
+                "│ Bus Reads                │ {:<14} │",
+                coherence.bus_reads
+            )?;
+            writeln!(
+                f,
+                "│ Bus Read Exclusives      │ {:<14} │",
+                coherence.bus_read_exclusives
+            )?;
+            writeln!(
+                f,
+                "│ Bus Upgrades             │ {:<14} │",
+                coherence.bus_upgrades
+            )?;
+            writeln!(
+                f,
+                "│ Invalidations            │ {:<14} │",
+                coherence.invalidations
+            )?;
+            writeln!(
+                f,
+                "│ Interventions            │ {:<14} │",
+                coherence.interventions
+            )?;
+            writeln!(
+                f,
+                "│ Coherence Misses         │ {:<14} │",
+                coherence.coherence_misses
+            )?;
+            writeln!(
+                f,
+                "│   True Sharing           │ {:<14} │",
+                coherence.true_sharing_misses
+            )?;
+            writeln!(
+                f,
+                "│   False Sharing          │ {:<14} │",
+                coherence.false_sharing_misses
+            )?;
+        }
+        if hierarchy {
+            writeln!(
+                f,
+                "│ Back-Invalidations       │ {:<14} │",
+                self.back_invalidations
+            )?;
+        }
+        writeln!(f, "└──────────────────────────┴────────────────┘")?;
+
+        if !self.dueling_winners.is_empty() {
+            writeln!(f, "Set Dueling Winners:")?;
+            for (access, policy) in &self.dueling_winners {
+                writeln!(f, "  from access {:<10} {}", access, policy)?;
+            }
+        }
+        if let Some(policy) = self.dueling_fallback {
+            writeln!(
+                f,
+                "Set Dueling: too few sets to duel, every set used {}",
+                policy
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the rows of the misses split by their cause.
+fn fmt_miss_kinds(f: &mut Formatter<'_>, kinds: &MissKinds) -> std::fmt::Result {
+    writeln!(f, "│   Compulsory             │ {:<14} │", kinds.compulsory)?;
+    writeln!(f, "│   Capacity               │ {:<14} │", kinds.capacity)?;
+    writeln!(f, "│   Conflict               │ {:<14} │", kinds.conflict)
+}
+
+impl Display for Logger {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if !self.cores.is_empty() {
+            for (core, core_log) in self.cores.iter().enumerate() {
+                writeln!(f, "Core {}", core)?;
+                core_log.fmt_level(f, false)?;
+            }
+            let total = self.cores_total();
+            let bus_transactions = total.coherence.unwrap_or_default().bus_transactions();
+            writeln!(f, "Bus Transactions: {}", bus_transactions)?;
+            writeln!(f, "Memory Write Words: {}", total.memory_writes)?;
+            writeln!(f, "Running Time: {:?}", self.slowest_core_time())?;
+            if let Some(directory) = self.directory {
+                writeln!(f, "Directory Lookups: {}", directory.lookups)?;
+                writeln!(f, "Directory Messages: {}", directory.messages)?;
+                writeln!(f, "Directory Evictions: {}", directory.evictions)?;
+            }
+            if !self.falsely_shared.is_empty() {
+                writeln!(f, "Most Falsely Shared Blocks")?;
+                writeln!(
+                    f,
+                    "┌────────────┬────────────────┬──────────────────────────┐"
+                )?;
+                writeln!(
+                    f,
+                    "│ Block      │ Misses         │ Cores                    │"
+                )?;
+                writeln!(
+                    f,
+                    "├────────────┼────────────────┼──────────────────────────┤"
+                )?;
+                for block in &self.falsely_shared {
+                    let cores: Vec<String> = block.cores.iter().map(usize::to_string).collect();
+                    writeln!(
+                        f,
+                        "│ {:<#10x} │ {:<14} │ {:<24} │",
+                        block.address,
+                        block.misses,
+                        cores.join(", ")
+                    )?;
+                }
+                writeln!(
+                    f,
+                    "└────────────┴────────────────┴──────────────────────────┘"
+                )?;
+            }
+        } else if self.lower_levels.is_empty() {
+            self.fmt_level(f, false)?;
+        } else {
+            writeln!(f, "L1")?;
+            self.fmt_level(f, true)?;
+            for (idx, level) in self.lower_levels.iter().enumerate() {
+                writeln!(f, "L{}", idx + 2)?;
+                level.fmt_level(f, true)?;
+            }
+            writeln!(f, "Total Running Time: {:?}", self.total_running_time())?;
+            if let Some((stored, capacity)) = self.effective_capacity {
+                writeln!(f, "Effective Capacity: {} of {} words", stored, capacity)?;
+            }
+        }
+
+        if let Some(ref reuse_histograms) = self.reuse_histograms {
+            write!(f, "{}", reuse_histograms)?;
+        }
+
+        Ok(())
     }
 }
 
 impl Add for Logger {
     type Output = Self;
-    fn add(self, other: Self) -> Self {
+    fn add(mut self, other: Self) -> Self {
+        self.dueling_winners.extend(other.dueling_winners);
+
         Self {
             instruction_references: self.instruction_references + other.instruction_references,
             data_references: self.data_references + other.data_references,
             instruction_misses: self.instruction_misses + other.instruction_misses,
             data_misses: self.data_misses + other.data_misses,
+            instruction_miss_kinds: self.instruction_miss_kinds + other.instruction_miss_kinds,
+            data_miss_kinds: self.data_miss_kinds + other.data_miss_kinds,
             memory_reads: self.memory_reads + other.memory_reads,
             memory_writes: self.memory_writes + other.memory_writes,
             running_time: self.running_time + other.running_time,
+            dueling_winners: self.dueling_winners,
+            dueling_fallback: self.dueling_fallback.or(other.dueling_fallback),
+            lower_levels: add_each(self.lower_levels, other.lower_levels),
+            ghost_hits: match (self.ghost_hits, other.ghost_hits) {
+                (Some((b1, b2)), Some((other_b1, other_b2))) => {
+                    Some((b1 + other_b1, b2 + other_b2))
+                }
+                (ghost_hits, None) | (None, ghost_hits) => ghost_hits,
+            },
+            victim_hits: match (self.victim_hits, other.victim_hits) {
+                (Some(hits), Some(other_hits)) => Some(hits + other_hits),
+                (victim_hits, None) | (None, victim_hits) => victim_hits,
+            },
+            prefetches: match (self.prefetches, other.prefetches) {
+                (Some(prefetches), Some(other_prefetches)) => Some(prefetches + other_prefetches),
+                (prefetches, None) | (None, prefetches) => prefetches,
+            },
+            reuse_histograms: self.reuse_histograms.or(other.reuse_histograms),
+            coherence: match (self.coherence, other.coherence) {
+                (Some(coherence), Some(other_coherence)) => Some(coherence + other_coherence),
+                (coherence, None) | (None, coherence) => coherence,
+            },
+            directory: match (self.directory, other.directory) {
+                (Some(directory), Some(other_directory)) => Some(directory + other_directory),
+                (directory, None) | (None, directory) => directory,
+            },
+            falsely_shared: if self.falsely_shared.is_empty() {
+                other.falsely_shared
+            } else {
+                self.falsely_shared
+            },
+            cores: add_each(self.cores, other.cores),
+            back_invalidations: self.back_invalidations + other.back_invalidations,
+            flushes: match (self.flushes, other.flushes) {
+                (Some(flushes), Some(other_flushes)) => Some(flushes + other_flushes),
+                (flushes, None) | (None, flushes) => flushes,
+            },
+            tag_storage: match (self.tag_storage, other.tag_storage) {
+                (Some(bits), Some(other_bits)) => Some(bits + other_bits),
+                (tag_storage, None) | (None, tag_storage) => tag_storage,
+            },
+            effective_capacity: match (self.effective_capacity, other.effective_capacity) {
+                (Some((stored, capacity)), Some((other_stored, other_capacity))) => {
+                    Some((stored + other_stored, capacity + other_capacity))
+                }
+                (effective_capacity, None) | (None, effective_capacity) => effective_capacity,
+            },
         }
     }
 }
+
+/// Adds the logs of `logs` and `other_logs` in the same position, keeping the
+/// logs of the longer one that have no pair.
+fn add_each(logs: Vec<Logger>, other_logs: Vec<Logger>) -> Vec<Logger> {
+    let mut other_logs = other_logs.into_iter();
+    let mut added: Vec<Logger> = logs
+        .into_iter()
+        .map(|log| match other_logs.next() {
+            Some(other_log) => log + other_log,
+            None => log,
+        })
+        .collect();
+
+    added.extend(other_logs);
+    added
+}
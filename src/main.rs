@@ -1,4 +1,5 @@
 use cache_simulator::cli_parser::ParsedArgs;
+use cache_simulator::multi_core_simulator::{self, ProtocolComparison};
 use cache_simulator::trace_simulator::TraceSimulator;
 use std::error::Error;
 
@@ -6,6 +7,12 @@ fn main() -> Result<(), Box<dyn Error>> {
     let args = std::env::args();
     let parsed_args = ParsedArgs::parse(args)?;
 
+    if multi_core_simulator::is_multi_core(&parsed_args)? {
+        let comparison = ProtocolComparison::run(&parsed_args)?;
+        println!("{}", comparison);
+        return Ok(());
+    }
+
     let simulator = TraceSimulator::new(parsed_args)?;
     let results = simulator.run()?;
 
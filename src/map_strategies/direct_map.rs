@@ -1,5 +1,6 @@
 use crate::cache_block::CacheBlock;
 use crate::map_strategies::{MapStrategy, MapStrategyFactory};
+use crate::replacement_policies::ReplacementPolicyFactory;
 use crate::MemoryAddress;
 use crate::WORD_SIZE;
 
@@ -7,7 +8,12 @@ use crate::WORD_SIZE;
 pub struct DirectMapFactory;
 
 impl MapStrategyFactory for DirectMapFactory {
-    fn generate(&self, block_size: usize, cache_size: usize) -> Box<dyn MapStrategy> {
+    fn generate(
+        &self,
+        block_size: usize,
+        cache_size: usize,
+        _replacement_policy_factory: &dyn ReplacementPolicyFactory,
+    ) -> Box<dyn MapStrategy> {
         let block_mask_size = (block_size.ilog2() + WORD_SIZE.ilog2()) as usize;
         let cache_mask_size = cache_size.ilog2() as usize;
 
@@ -18,6 +24,10 @@ impl MapStrategyFactory for DirectMapFactory {
 
         Box::new(map_strategy)
     }
+
+    fn ways(&self, _cache_size: usize) -> usize {
+        1
+    }
 }
 
 pub struct DirectMap {
@@ -34,4 +44,19 @@ impl MapStrategy for DirectMap {
     fn get_tag(&self, address: MemoryAddress) -> MemoryAddress {
         address >> (self.block_mask_size + self.cache_mask_size)
     }
+
+    fn get_address(&self, tag: MemoryAddress, block_index: usize) -> MemoryAddress {
+        let index = (block_index as MemoryAddress) << self.block_mask_size;
+        (tag << (self.block_mask_size + self.cache_mask_size)) | index
+    }
+
+    fn index_bits(&self) -> u32 {
+        self.cache_mask_size as u32
+    }
+
+    fn find(&mut self, address: MemoryAddress, blocks: &[CacheBlock]) -> Option<usize> {
+        let block_index = self.map(address, blocks) as usize;
+        let block = &blocks[block_index];
+        (block.valid && block.is_match(self.get_tag(address))).then_some(block_index)
+    }
 }
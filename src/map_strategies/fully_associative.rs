@@ -1,6 +1,9 @@
+use std::collections::{BTreeSet, HashMap};
+
 use crate::cache_block::CacheBlock;
-use crate::lru::Lru;
+use crate::logger::Logger;
 use crate::map_strategies::{MapStrategy, MapStrategyFactory};
+use crate::replacement_policies::{ReplacementPolicy, ReplacementPolicyFactory};
 use crate::MemoryAddress;
 
 use crate::WORD_SIZE;
@@ -9,42 +12,97 @@ use crate::WORD_SIZE;
 pub struct FullyAssociativeFactory;
 
 impl MapStrategyFactory for FullyAssociativeFactory {
-    fn generate(&self, block_size: usize, cache_size: usize) -> Box<dyn MapStrategy> {
+    fn generate(
+        &self,
+        block_size: usize,
+        cache_size: usize,
+        replacement_policy_factory: &dyn ReplacementPolicyFactory,
+    ) -> Box<dyn MapStrategy> {
         let block_mask_size = (block_size.ilog2() + WORD_SIZE.ilog2()) as usize;
-        let replacement_policy = Lru::new(cache_size);
+        let replacement_policy = replacement_policy_factory.generate(1, cache_size);
 
         let map_strategy = FullyAssociative {
             block_mask_size,
             replacement_policy,
+            ways_by_tag: HashMap::new(),
+            empty_ways: (0..cache_size).collect(),
         };
 
         Box::new(map_strategy)
     }
+
+    fn ways(&self, cache_size: usize) -> usize {
+        cache_size
+    }
 }
 
+/// Maps any block to any position of the cache.
+///
+/// To avoid scanning the whole cache on every access, it remembers where each
+/// tag is and which ways are empty, which the cache keeps up to date through
+/// `place` and `invalidate`.
 pub struct FullyAssociative {
     block_mask_size: usize,
-    replacement_policy: Lru,
+    replacement_policy: Box<dyn ReplacementPolicy>,
+    ways_by_tag: HashMap<MemoryAddress, usize>,
+    empty_ways: BTreeSet<usize>,
 }
 
 impl MapStrategy for FullyAssociative {
-    fn map(&mut self, address: MemoryAddress, blocks: &[CacheBlock]) -> MemoryAddress {
+    fn map(&mut self, address: MemoryAddress, _blocks: &[CacheBlock]) -> MemoryAddress {
         let tag = self.get_tag(address);
-        let possible_block = blocks
-            .iter()
-            .position(|block| block.valid && block.is_match(tag));
-        if let Some(idx) = possible_block {
-            self.replacement_policy.mark_use(idx);
-            return idx as MemoryAddress;
+
+        if let Some(&way) = self.ways_by_tag.get(&tag) {
+            self.replacement_policy.touch(0, way);
+            return way as MemoryAddress;
         }
 
-        let result = self.replacement_policy.get_lru();
-        self.replacement_policy.mark_use(result);
+        self.replacement_policy.miss(0, tag);
+        let way = match self.empty_ways.first() {
+            Some(&way) => way,
+            None => self.replacement_policy.victim(0),
+        };
 
-        result as MemoryAddress
+        way as MemoryAddress
     }
 
     fn get_tag(&self, address: MemoryAddress) -> MemoryAddress {
         address >> self.block_mask_size
     }
+
+    fn get_address(&self, tag: MemoryAddress, _block_index: usize) -> MemoryAddress {
+        tag << self.block_mask_size
+    }
+
+    fn index_bits(&self) -> u32 {
+        0
+    }
+
+    fn find(&mut self, address: MemoryAddress, _blocks: &[CacheBlock]) -> Option<usize> {
+        self.ways_by_tag.get(&self.get_tag(address)).copied()
+    }
+
+    fn touch(&mut self, block_index: usize) {
+        self.replacement_policy.touch(0, block_index);
+    }
+
+    fn place(&mut self, block_index: usize, tag: MemoryAddress, blocks: &[CacheBlock]) {
+        let replaced_block = &blocks[block_index];
+        if replaced_block.valid {
+            self.ways_by_tag.remove(&replaced_block.tag);
+        }
+        self.ways_by_tag.insert(tag, block_index);
+        self.empty_ways.remove(&block_index);
+        self.replacement_policy.fill(0, block_index, tag);
+    }
+
+    fn invalidate(&mut self, block_index: usize, blocks: &[CacheBlock]) {
+        self.ways_by_tag.remove(&blocks[block_index].tag);
+        self.empty_ways.insert(block_index);
+        self.replacement_policy.invalidate(0, block_index);
+    }
+
+    fn report(&self, log: &mut Logger) {
+        self.replacement_policy.report(log);
+    }
 }
@@ -1,6 +1,8 @@
 use std::fmt;
 
 use crate::cache_block::CacheBlock;
+use crate::logger::Logger;
+use crate::replacement_policies::{ReplacementPolicy, ReplacementPolicyFactory};
 use crate::MemoryAddress;
 
 pub mod direct_map;
@@ -10,7 +12,18 @@ pub mod set_associative;
 /// A factory trait for creating mapping strategies.
 pub trait MapStrategyFactory: fmt::Debug {
     /// Generates a new mapping strategy instance.
-    fn generate(&self, block_size: usize, cache_size: usize) -> Box<dyn MapStrategy>;
+    ///
+    /// Strategies that have to choose between several blocks on a miss use
+    /// a policy generated by `replacement_policy_factory` to do it.
+    fn generate(
+        &self,
+        block_size: usize,
+        cache_size: usize,
+        replacement_policy_factory: &dyn ReplacementPolicyFactory,
+    ) -> Box<dyn MapStrategy>;
+
+    /// Returns the blocks in each set of a cache of `cache_size` blocks.
+    fn ways(&self, cache_size: usize) -> usize;
 }
 
 /// Defines the behavior of a common mapping strategy.
@@ -26,6 +39,11 @@ pub trait MapStrategy {
     /// that block. The caller is responsible for verifying the presence of
     /// the data by comparing the block's tag with the tag returned by
     /// the `get_tag` function.
+    ///
+    /// On a miss the replacement policy already chooses the block, so the
+    /// caller must store the data there and tell the strategy with `place`.
+    /// Accesses that will not place the data should use `find` and `touch`
+    /// instead.
     fn map(&mut self, address: MemoryAddress, blocks: &[CacheBlock]) -> MemoryAddress;
 
     /// Extracts the tag from a given memory address.
@@ -33,4 +51,59 @@ pub trait MapStrategy {
     /// The tag is a portion of the memory address used to identify
     /// whether a specific common block contains the desired data.
     fn get_tag(&self, address: MemoryAddress) -> MemoryAddress;
+
+    /// Rebuilds the address of the first word of the block with `tag` that
+    /// is stored in `block_index`.
+    fn get_address(&self, tag: MemoryAddress, block_index: usize) -> MemoryAddress;
+
+    /// Returns the bits of an address that select the set or the block, which
+    /// the tags do not need to store.
+    fn index_bits(&self) -> u32;
+
+    /// Returns the index of the valid block that holds `address`, if any,
+    /// without counting it as an access.
+    fn find(&mut self, address: MemoryAddress, blocks: &[CacheBlock]) -> Option<usize>;
+
+    /// Counts a hit on the block stored in `block_index` as an access, for
+    /// hits found with `find`.
+    fn touch(&mut self, _block_index: usize) {}
+
+    /// Records that the block with `tag` is stored in `block_index`, replacing
+    /// the block there if it is valid. The cache calls this right before
+    /// updating the block.
+    fn place(&mut self, _block_index: usize, _tag: MemoryAddress, _blocks: &[CacheBlock]) {}
+
+    /// Forgets the block stored in `block_index`. The cache calls this right
+    /// before the block stops being valid.
+    fn invalidate(&mut self, _block_index: usize, _blocks: &[CacheBlock]) {}
+
+    /// Adds the statistics collected by the strategy to the log.
+    fn report(&self, _log: &mut Logger) {}
+}
+
+/// Looks for `tag` in the blocks of `set` and returns the way where it is,
+/// or the way where it should be placed.
+///
+/// Empty ways are used before asking the policy for a victim. The policy
+/// hears about the new block when it is placed.
+fn associative_map(
+    replacement_policy: &mut dyn ReplacementPolicy,
+    set: usize,
+    blocks: &[CacheBlock],
+    tag: MemoryAddress,
+) -> usize {
+    let possible_block = blocks
+        .iter()
+        .position(|block| block.valid && block.is_match(tag));
+
+    if let Some(way) = possible_block {
+        replacement_policy.touch(set, way);
+        return way;
+    }
+
+    replacement_policy.miss(set, tag);
+    blocks
+        .iter()
+        .position(|block| !block.valid)
+        .unwrap_or_else(|| replacement_policy.victim(set))
 }
@@ -1,6 +1,7 @@
 use crate::cache_block::CacheBlock;
-use crate::lru::Lru;
-use crate::map_strategies::{MapStrategy, MapStrategyFactory};
+use crate::logger::Logger;
+use crate::map_strategies::{associative_map, MapStrategy, MapStrategyFactory};
+use crate::replacement_policies::{ReplacementPolicy, ReplacementPolicyFactory};
 use crate::MemoryAddress;
 use crate::WORD_SIZE;
 
@@ -10,10 +11,15 @@ pub struct SetAssociativeFactory {
 }
 
 impl MapStrategyFactory for SetAssociativeFactory {
-    fn generate(&self, block_size: usize, cache_size: usize) -> Box<dyn MapStrategy> {
+    fn generate(
+        &self,
+        block_size: usize,
+        cache_size: usize,
+        replacement_policy_factory: &dyn ReplacementPolicyFactory,
+    ) -> Box<dyn MapStrategy> {
         let block_mask_size = (block_size.ilog2() + WORD_SIZE.ilog2()) as usize;
         let replacement_policy =
-            vec![Lru::new(cache_size / self.sets); self.sets].into_boxed_slice();
+            replacement_policy_factory.generate(self.sets, cache_size / self.sets);
         let set_mask_size = self.sets.ilog2() as usize;
 
         let map_strategy = SetAssociative {
@@ -26,17 +32,27 @@ impl MapStrategyFactory for SetAssociativeFactory {
 
         Box::new(map_strategy)
     }
+
+    fn ways(&self, cache_size: usize) -> usize {
+        cache_size / self.sets
+    }
 }
 
 pub struct SetAssociative {
     cache_size: usize,
     block_mask_size: usize,
     set_mask_size: usize,
-    replacement_policy: Box<[Lru]>,
+    replacement_policy: Box<dyn ReplacementPolicy>,
     sets: usize,
 }
 
 impl SetAssociative {
+    /// Returns the set of `block_index` and its way inside the set.
+    fn split_index(&self, block_index: usize) -> (usize, usize) {
+        let elements = self.cache_size / self.sets;
+        (block_index / elements, block_index % elements)
+    }
+
     fn get_set(&self, mut address: MemoryAddress) -> MemoryAddress {
         let mask = (1 << (self.block_mask_size + self.set_mask_size)) - 1;
         address &= mask;
@@ -52,22 +68,50 @@ impl MapStrategy for SetAssociative {
         let end = start + elements;
         let tag = self.get_tag(address);
 
-        let possible_block = blocks[start..end]
+        let way = associative_map(&mut *self.replacement_policy, set, &blocks[start..end], tag);
+
+        (start + way) as MemoryAddress
+    }
+
+    fn get_tag(&self, address: MemoryAddress) -> MemoryAddress {
+        address >> self.block_mask_size
+    }
+
+    fn get_address(&self, tag: MemoryAddress, _block_index: usize) -> MemoryAddress {
+        tag << self.block_mask_size
+    }
+
+    fn index_bits(&self) -> u32 {
+        self.set_mask_size as u32
+    }
+
+    fn find(&mut self, address: MemoryAddress, blocks: &[CacheBlock]) -> Option<usize> {
+        let elements = self.cache_size / self.sets;
+        let start = self.get_set(address) as usize * elements;
+        let tag = self.get_tag(address);
+
+        blocks[start..start + elements]
             .iter()
-            .position(|block| block.valid && block.is_match(tag));
+            .position(|block| block.valid && block.is_match(tag))
+            .map(|way| start + way)
+    }
 
-        if let Some(idx) = possible_block {
-            self.replacement_policy[set].mark_use(idx);
-            return (start + idx) as MemoryAddress;
-        }
+    fn touch(&mut self, block_index: usize) {
+        let (set, way) = self.split_index(block_index);
+        self.replacement_policy.touch(set, way);
+    }
 
-        let result = self.replacement_policy[set].get_lru();
-        self.replacement_policy[set].mark_use(result);
+    fn place(&mut self, block_index: usize, tag: MemoryAddress, _blocks: &[CacheBlock]) {
+        let (set, way) = self.split_index(block_index);
+        self.replacement_policy.fill(set, way, tag);
+    }
 
-        (start + result) as MemoryAddress
+    fn invalidate(&mut self, block_index: usize, _blocks: &[CacheBlock]) {
+        let (set, way) = self.split_index(block_index);
+        self.replacement_policy.invalidate(set, way);
     }
 
-    fn get_tag(&self, address: MemoryAddress) -> MemoryAddress {
-        address >> self.block_mask_size
+    fn report(&self, log: &mut Logger) {
+        self.replacement_policy.report(log);
     }
 }
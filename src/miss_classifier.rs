@@ -0,0 +1,75 @@
+use std::collections::HashSet;
+
+use crate::cache_block::CacheBlock;
+use crate::map_strategies::fully_associative::FullyAssociativeFactory;
+use crate::map_strategies::{MapStrategy, MapStrategyFactory};
+use crate::replacement_policies::lru::LruFactory;
+use crate::{MemoryAddress, WORD_SIZE};
+
+/// Cause of a miss, following the three C model.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MissKind {
+    /// First access to the block.
+    Compulsory,
+    /// A fully associative cache of the same size would have missed too.
+    Capacity,
+    /// Caused by the mapping, a fully associative cache would have hit.
+    Conflict,
+}
+
+/// Classifies the misses of a cache, running every access through a fully
+/// associative LRU cache with the same number of blocks.
+pub struct MissClassifier {
+    block_mask_size: u32,
+    /// Blocks that were accessed at least once.
+    seen: HashSet<MemoryAddress>,
+    shadow: Box<dyn MapStrategy>,
+    shadow_blocks: Box<[CacheBlock]>,
+}
+
+impl MissClassifier {
+    pub fn new(block_size: usize, cache_size: usize) -> Self {
+        let shadow = FullyAssociativeFactory.generate(block_size, cache_size, &LruFactory);
+
+        Self {
+            block_mask_size: block_size.ilog2() + WORD_SIZE.ilog2(),
+            seen: HashSet::new(),
+            shadow,
+            shadow_blocks: vec![CacheBlock::default(); cache_size].into_boxed_slice(),
+        }
+    }
+
+    /// Records an access to `address` that hit in the cache.
+    pub fn hit(&mut self, address: MemoryAddress) {
+        self.access(address);
+    }
+
+    /// Records an access to `address` that missed in the cache, and returns
+    /// the cause of the miss.
+    pub fn miss(&mut self, address: MemoryAddress) -> MissKind {
+        match self.access(address) {
+            (true, _) => MissKind::Compulsory,
+            (false, false) => MissKind::Capacity,
+            (false, true) => MissKind::Conflict,
+        }
+    }
+
+    /// Runs an access through the shadow cache, and returns whether it was
+    /// the first access to the block and whether the shadow cache hit.
+    fn access(&mut self, address: MemoryAddress) -> (bool, bool) {
+        let first_touch = self.seen.insert(address >> self.block_mask_size);
+
+        let tag = self.shadow.get_tag(address);
+        let block_index = self.shadow.map(address, &self.shadow_blocks) as usize;
+        let block = &self.shadow_blocks[block_index];
+        let shadow_hit = block.valid && block.is_match(tag);
+        if !shadow_hit {
+            self.shadow.place(block_index, tag, &self.shadow_blocks);
+            let block = &mut self.shadow_blocks[block_index];
+            block.valid = true;
+            block.tag = tag;
+        }
+
+        (first_touch, shadow_hit)
+    }
+}
@@ -0,0 +1,244 @@
+use crate::cache::Cache;
+use crate::cli_parser::ParsedArgs;
+use crate::coherence::{Bus, CoherenceProtocol};
+use crate::logger::Logger;
+use crate::trace_reader::{self, TraceReader, TraceRecord};
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::BufReader;
+use std::rc::Rc;
+
+/// Falsely shared blocks listed in the results.
+const FALSELY_SHARED_BLOCKS: usize = 10;
+
+/// Records of every core, in the order they are simulated. The accesses
+/// carry the core that makes them.
+type Records = Box<dyn Iterator<Item = Result<TraceRecord, Box<dyn Error>>>>;
+
+/// Private cache of a core, with its log.
+type PrivateCache = (Rc<RefCell<Cache>>, Rc<RefCell<Logger>>);
+
+/// Simulates several cores, each with a private cache, kept coherent through
+/// a shared bus.
+pub struct MultiCoreSimulator {
+    cores: Vec<PrivateCache>,
+    records: Records,
+    word_size: usize,
+    bus: Rc<RefCell<Bus>>,
+}
+
+impl MultiCoreSimulator {
+    /// Builds the caches of the cores in `args`, kept coherent with
+    /// `protocol`.
+    pub fn new(
+        args: &ParsedArgs,
+        protocol: Rc<dyn CoherenceProtocol>,
+    ) -> Result<MultiCoreSimulator, Box<dyn Error>> {
+        check_args(args)?;
+
+        let (core_count, records): (usize, Records) = if args.core_traces.is_empty() {
+            let mut trace = TraceReader::open_with(&args.file_path, args)?;
+            let threads = trace.threads()?.unwrap_or(1);
+            (
+                threads,
+                Box::new(std::iter::from_fn(move || trace.next_record())),
+            )
+        } else {
+            let traces = args
+                .core_traces
+                .iter()
+                .map(|trace| TraceReader::open_with(trace, args))
+                .collect::<Result<Vec<_>, _>>()?;
+            (traces.len(), Box::new(interleave(traces)))
+        };
+
+        let bus = match args.directory {
+            Some(format) => Bus::with_directory(protocol, format, args.block_size),
+            None => Bus::new(protocol),
+        };
+        let bus = Rc::new(RefCell::new(bus));
+        let cores = (0..core_count)
+            .map(|_| {
+                let log = Rc::new(RefCell::new(Logger::default()));
+                let mut cache = Cache::new(
+                    args.block_size,
+                    args.cache_size,
+                    &*args.map_strategy_factory,
+                    &*args.replacement_policy_factory,
+                    args.write_policy,
+                    args.write_miss_policy,
+                    Rc::clone(&log),
+                );
+                cache.set_latency(args.latency);
+                if let Some(address_width) = args.address_width {
+                    let address_width =
+                        trace_reader::cache_address_width(address_width, args.word_size);
+                    log.borrow_mut().tag_storage = Some(cache.tag_storage(address_width));
+                }
+
+                let cache = Rc::new(RefCell::new(cache));
+                Bus::connect(&bus, &cache);
+                (cache, log)
+            })
+            .collect();
+
+        Ok(MultiCoreSimulator {
+            cores,
+            records,
+            word_size: args.word_size,
+            bus,
+        })
+    }
+
+    pub fn run(mut self) -> Result<Logger, Box<dyn Error>> {
+        for record in self.records.by_ref() {
+            match record? {
+                TraceRecord::Access((core, access_type, address)) => {
+                    let (cache, _) = &self.cores[core];
+                    cache.borrow_mut().access(access_type, address);
+                }
+                TraceRecord::Flush => {
+                    for (cache, log) in &self.cores {
+                        log.borrow_mut().flush();
+                        cache.borrow_mut().flush();
+                    }
+                }
+            }
+        }
+
+        let mut logs = Logger::default();
+        for (cache, log) in &self.cores {
+            cache.borrow().report();
+            logs.cores.push(log.take());
+        }
+        logs.directory = self.bus.borrow().directory_stats();
+        logs.falsely_shared = self.bus.borrow().most_falsely_shared(FALSELY_SHARED_BLOCKS);
+        for block in &mut logs.falsely_shared {
+            block.address = trace_reader::trace_address(block.address, self.word_size);
+        }
+
+        Ok(logs)
+    }
+}
+
+/// Returns whether `args` asks for several cores, either with a trace per
+/// core or with a trace whose header declares several threads.
+pub fn is_multi_core(args: &ParsedArgs) -> Result<bool, Box<dyn Error>> {
+    if !args.core_traces.is_empty() {
+        return Ok(true);
+    }
+
+    let threads = TraceReader::open_with(&args.file_path, args)?.threads()?;
+    Ok(threads.is_some_and(|threads| threads > 1))
+}
+
+/// Checks that `args` only uses options the private caches of the cores
+/// support.
+fn check_args(args: &ParsedArgs) -> Result<(), Box<dyn Error>> {
+    if args.split_i_d
+        || args.victim_cache.is_some()
+        || args.prefetcher_factory.is_some()
+        || args.reuse_histograms
+    {
+        return Err("-split, -vc, -pf and -reuse can not be used with several cores".into());
+    }
+    if !args.lower_levels.is_empty() {
+        return Err("Lower levels can not be used with several cores".into());
+    }
+    if args.replacement_policy_factory.lookahead().is_some() {
+        return Err("OPT can not be used with several cores".into());
+    }
+
+    Ok(())
+}
+
+/// Results of replaying the same multi-core traces under several coherence
+/// protocols.
+pub struct ProtocolComparison {
+    runs: Vec<(&'static str, Logger)>,
+}
+
+impl ProtocolComparison {
+    /// Simulates the traces in `args` once for each of its protocols.
+    pub fn run(args: &ParsedArgs) -> Result<ProtocolComparison, Box<dyn Error>> {
+        let runs = args
+            .coherence_protocols
+            .iter()
+            .map(|protocol| {
+                let logs = MultiCoreSimulator::new(args, Rc::clone(protocol))?.run()?;
+                Ok((protocol.name(), logs))
+            })
+            .collect::<Result<_, Box<dyn Error>>>()?;
+
+        Ok(ProtocolComparison { runs })
+    }
+}
+
+impl Display for ProtocolComparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let [(_, logs)] = self.runs.as_slice() {
+            return write!(f, "{}", logs);
+        }
+
+        for (name, logs) in &self.runs {
+            writeln!(f, "Protocol {}", name)?;
+            writeln!(f, "{}", logs)?;
+        }
+
+        writeln!(
+            f,
+            "┌──────────┬──────────────────┬──────────────────┬────────────────┐"
+        )?;
+        writeln!(
+            f,
+            "│ Protocol │ Bus Transactions │ Memory Writes    │ Running Time   │"
+        )?;
+        writeln!(
+            f,
+            "├──────────┼──────────────────┼──────────────────┼────────────────┤"
+        )?;
+        for (name, logs) in &self.runs {
+            let total = logs.cores_total();
+            writeln!(
+                f,
+                "│ {:<8} │ {:<16} │ {:<16} │ {:<14?} │",
+                name,
+                total.coherence.unwrap_or_default().bus_transactions(),
+                total.memory_writes,
+                logs.slowest_core_time()
+            )?;
+        }
+        writeln!(
+            f,
+            "└──────────┴──────────────────┴──────────────────┴────────────────┘"
+        )
+    }
+}
+
+/// Takes one record from every trace in turn, skipping the traces that
+/// already ended. The accesses of each trace are made by its core.
+fn interleave(
+    mut traces: Vec<TraceReader<BufReader<File>>>,
+) -> impl Iterator<Item = Result<TraceRecord, Box<dyn Error>>> {
+    let mut finished = vec![false; traces.len()];
+    let mut core = traces.len().saturating_sub(1);
+
+    std::iter::from_fn(move || {
+        for _ in 0..traces.len() {
+            core = (core + 1) % traces.len();
+            if finished[core] {
+                continue;
+            }
+            match traces[core].next_record() {
+                Some(Ok(TraceRecord::Access((_, access_type, address)))) => {
+                    return Some(Ok(TraceRecord::Access((core, access_type, address))))
+                }
+                Some(record) => return Some(record),
+                None => finished[core] = true,
+            }
+        }
+        None
+    })
+}
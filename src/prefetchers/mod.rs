@@ -0,0 +1,46 @@
+use std::fmt;
+
+use crate::{MemoryAddress, WORD_SIZE};
+
+pub mod next_line;
+pub mod stream;
+pub mod stride;
+
+/// A factory trait for creating prefetchers.
+pub trait PrefetcherFactory: fmt::Debug {
+    /// Generates a new prefetcher for a cache with blocks of `block_size`
+    /// words.
+    fn generate(&self, block_size: usize) -> Box<dyn Prefetcher>;
+}
+
+/// How the cache served a demand access.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    Miss,
+    Hit,
+    /// Hit on a block that a prefetch brought and was not used before.
+    PrefetchHit,
+}
+
+/// Defines the behavior of a hardware prefetcher.
+pub trait Prefetcher {
+    /// Called after every demand access to the cache. Returns the addresses
+    /// of the blocks that should be prefetched, the cache ignores the ones it
+    /// already has.
+    fn access(&mut self, address: MemoryAddress, outcome: Outcome) -> Vec<MemoryAddress>;
+}
+
+/// Returns the size of a block of `block_size` words in bytes.
+fn block_bytes(block_size: usize) -> MemoryAddress {
+    (block_size * WORD_SIZE) as MemoryAddress
+}
+
+/// Returns the addresses of the `degree` blocks that follow `address` going
+/// `stride` bytes at a time, stopping at the ends of the address space.
+fn blocks_ahead(address: MemoryAddress, stride: i64, degree: usize) -> Vec<MemoryAddress> {
+    (1..=degree as i64)
+        .map_while(|step| {
+            MemoryAddress::try_from(address as i128 + stride as i128 * step as i128).ok()
+        })
+        .collect()
+}
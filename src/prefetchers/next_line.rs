@@ -0,0 +1,36 @@
+use crate::prefetchers::{block_bytes, blocks_ahead, Outcome, Prefetcher, PrefetcherFactory};
+use crate::MemoryAddress;
+
+#[derive(Debug)]
+pub struct NextLineFactory {
+    /// Blocks fetched after each trigger.
+    pub degree: usize,
+}
+
+impl PrefetcherFactory for NextLineFactory {
+    fn generate(&self, block_size: usize) -> Box<dyn Prefetcher> {
+        Box::new(NextLine {
+            block_bytes: block_bytes(block_size),
+            degree: self.degree,
+        })
+    }
+}
+
+/// Fetches the `degree` blocks that follow a missing block. The first hit on
+/// a prefetched block fetches the next ones too, so a sequential run keeps
+/// the prefetches ahead of it.
+pub struct NextLine {
+    block_bytes: MemoryAddress,
+    degree: usize,
+}
+
+impl Prefetcher for NextLine {
+    fn access(&mut self, address: MemoryAddress, outcome: Outcome) -> Vec<MemoryAddress> {
+        if outcome == Outcome::Hit {
+            return Vec::new();
+        }
+
+        let block_address = address - address % self.block_bytes;
+        blocks_ahead(block_address, self.block_bytes as i64, self.degree)
+    }
+}
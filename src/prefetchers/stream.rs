@@ -0,0 +1,60 @@
+use crate::prefetchers::{block_bytes, blocks_ahead, Outcome, Prefetcher, PrefetcherFactory};
+use crate::MemoryAddress;
+
+#[derive(Debug)]
+pub struct StreamFactory {
+    /// Streams followed at the same time.
+    pub streams: usize,
+    /// Blocks each stream keeps fetched ahead of the last one used.
+    pub depth: usize,
+}
+
+impl PrefetcherFactory for StreamFactory {
+    fn generate(&self, block_size: usize) -> Box<dyn Prefetcher> {
+        Box::new(Stream {
+            block_bytes: block_bytes(block_size),
+            depth: self.depth,
+            heads: Vec::with_capacity(self.streams),
+            streams: self.streams,
+        })
+    }
+}
+
+/// Stream buffers that fill the cache. A miss outside every stream starts a
+/// new one that fetches the next `depth` blocks, replacing the least recently
+/// used stream, and using a block of a stream moves it forward.
+pub struct Stream {
+    block_bytes: MemoryAddress,
+    depth: usize,
+    /// First block each stream has fetched and not used yet, from the least
+    /// to the most recently used stream.
+    heads: Vec<MemoryAddress>,
+    streams: usize,
+}
+
+impl Prefetcher for Stream {
+    fn access(&mut self, address: MemoryAddress, outcome: Outcome) -> Vec<MemoryAddress> {
+        let block_address = address - address % self.block_bytes;
+        let window = self.block_bytes * self.depth as MemoryAddress;
+        let stream = self
+            .heads
+            .iter()
+            .position(|&head| block_address >= head && block_address - head < window);
+
+        match (stream, outcome) {
+            (Some(stream), Outcome::Miss | Outcome::PrefetchHit) => {
+                self.heads.remove(stream);
+            }
+            (None, Outcome::Miss) if self.streams > 0 => {
+                if self.heads.len() == self.streams {
+                    self.heads.remove(0);
+                }
+            }
+            _ => return Vec::new(),
+        }
+
+        self.heads
+            .push(block_address.saturating_add(self.block_bytes));
+        blocks_ahead(block_address, self.block_bytes as i64, self.depth)
+    }
+}
@@ -0,0 +1,86 @@
+use crate::prefetchers::{block_bytes, blocks_ahead, Outcome, Prefetcher, PrefetcherFactory};
+use crate::MemoryAddress;
+
+/// Bits of the address that select the region of an access.
+const REGION_BITS: u32 = 12;
+
+/// Regions whose strides are remembered at the same time.
+const TABLE_ENTRIES: usize = 64;
+
+/// Times a stride has to repeat before it is used.
+const CONFIDENCE_THRESHOLD: u8 = 2;
+
+#[derive(Debug)]
+pub struct StrideFactory {
+    /// Strides fetched ahead of each access.
+    pub degree: usize,
+}
+
+impl PrefetcherFactory for StrideFactory {
+    fn generate(&self, block_size: usize) -> Box<dyn Prefetcher> {
+        Box::new(Stride {
+            block_bytes: block_bytes(block_size),
+            degree: self.degree,
+            table: vec![None; TABLE_ENTRIES].into_boxed_slice(),
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    region: MemoryAddress,
+    last_block: MemoryAddress,
+    /// Distance between the last two accesses, in bytes.
+    stride: i64,
+    confidence: u8,
+}
+
+/// Detects constant strides between the blocks accessed in each 4 KiB region,
+/// without knowing which instruction made the access.
+///
+/// Regions share a small direct mapped table, and once a stride repeats the
+/// next `degree` blocks along it are fetched on every access.
+pub struct Stride {
+    block_bytes: MemoryAddress,
+    degree: usize,
+    table: Box<[Option<Entry>]>,
+}
+
+impl Prefetcher for Stride {
+    fn access(&mut self, address: MemoryAddress, _outcome: Outcome) -> Vec<MemoryAddress> {
+        let block_address = address - address % self.block_bytes;
+        let region = address >> REGION_BITS;
+        let slot = &mut self.table[region as usize % TABLE_ENTRIES];
+
+        let entry = match slot {
+            Some(entry) if entry.region == region => entry,
+            _ => {
+                *slot = Some(Entry {
+                    region,
+                    last_block: block_address,
+                    stride: 0,
+                    confidence: 0,
+                });
+                return Vec::new();
+            }
+        };
+
+        let stride = block_address as i64 - entry.last_block as i64;
+        if stride == 0 {
+            return Vec::new();
+        }
+
+        if stride == entry.stride {
+            entry.confidence = (entry.confidence + 1).min(CONFIDENCE_THRESHOLD + 1);
+        } else {
+            entry.stride = stride;
+            entry.confidence = 0;
+        }
+        entry.last_block = block_address;
+
+        if entry.confidence < CONFIDENCE_THRESHOLD {
+            return Vec::new();
+        }
+        blocks_ahead(block_address, stride, self.degree)
+    }
+}
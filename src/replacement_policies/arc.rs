@@ -0,0 +1,192 @@
+use std::collections::VecDeque;
+
+use crate::logger::Logger;
+use crate::replacement_policies::{ReplacementPolicy, ReplacementPolicyFactory};
+use crate::MemoryAddress;
+
+#[derive(Debug)]
+pub struct ArcFactory;
+
+impl ReplacementPolicyFactory for ArcFactory {
+    fn generate(&self, sets: usize, ways: usize) -> Box<dyn ReplacementPolicy> {
+        let sets = (0..sets).map(|_| ArcSet::default()).collect();
+
+        Box::new(Arc {
+            ways,
+            sets,
+            b1_hits: 0,
+            b2_hits: 0,
+        })
+    }
+}
+
+/// Where a missing tag was seen last.
+#[derive(Clone, Copy, PartialEq)]
+enum Ghost {
+    /// The tag is not remembered, so the block is new.
+    None,
+    B1(usize),
+    B2(usize),
+}
+
+#[derive(Default)]
+struct ArcSet {
+    /// Resident blocks seen once recently, from least to most recently used.
+    t1: VecDeque<(usize, MemoryAddress)>,
+    /// Resident blocks seen at least twice recently.
+    t2: VecDeque<(usize, MemoryAddress)>,
+    /// Tags of the blocks recently evicted from `t1`.
+    b1: VecDeque<MemoryAddress>,
+    /// Tags of the blocks recently evicted from `t2`.
+    b2: VecDeque<MemoryAddress>,
+    /// Target size of `t1`.
+    p: usize,
+    /// Tag of the last miss, which the victim is chosen for.
+    missing_tag: Option<MemoryAddress>,
+}
+
+impl ArcSet {
+    fn remove_way(&mut self, way: usize) -> Option<(usize, MemoryAddress)> {
+        for list in [&mut self.t1, &mut self.t2] {
+            if let Some(idx) = list.iter().position(|&(w, _)| w == way) {
+                return list.remove(idx);
+            }
+        }
+
+        None
+    }
+
+    fn ghost(&self, tag: Option<MemoryAddress>) -> Ghost {
+        let Some(tag) = tag else {
+            return Ghost::None;
+        };
+        if let Some(idx) = self.b1.iter().position(|&t| t == tag) {
+            Ghost::B1(idx)
+        } else if let Some(idx) = self.b2.iter().position(|&t| t == tag) {
+            Ghost::B2(idx)
+        } else {
+            Ghost::None
+        }
+    }
+
+    /// Returns the target size of `t1` once a miss on `ghost` adapts it.
+    fn adapted_p(&self, ghost: Ghost, ways: usize) -> usize {
+        match ghost {
+            Ghost::None => self.p,
+            Ghost::B1(_) => (self.p + (self.b2.len() / self.b1.len()).max(1)).min(ways),
+            Ghost::B2(_) => self
+                .p
+                .saturating_sub((self.b1.len() / self.b2.len()).max(1)),
+        }
+    }
+
+    /// Whether a new block finds the recency side full of resident blocks,
+    /// so the victim must leave without being remembered.
+    fn recent_full(&self, ghost: Ghost, ways: usize) -> bool {
+        ghost == Ghost::None && self.t1.len() >= ways
+    }
+}
+
+/// Adaptive replacement cache (ARC).
+///
+/// Every set splits its blocks between those seen once (T1) and those seen
+/// more than once (T2), both managed with LRU, and remembers the tags of the
+/// blocks recently evicted from each one in the ghost lists B1 and B2. A miss
+/// on a ghost list means that side was too small, so the target size of T1
+/// grows on B1 hits and shrinks on B2 hits.
+///
+/// Choosing a victim does not change the lists, which only move once the
+/// missing block is placed.
+pub struct Arc {
+    ways: usize,
+    sets: Box<[ArcSet]>,
+    b1_hits: u128,
+    b2_hits: u128,
+}
+
+impl ReplacementPolicy for Arc {
+    fn touch(&mut self, set: usize, way: usize) {
+        let arc_set = &mut self.sets[set];
+        let block = arc_set.remove_way(way).unwrap();
+        arc_set.t2.push_back(block);
+    }
+
+    fn miss(&mut self, set: usize, tag: MemoryAddress) {
+        self.sets[set].missing_tag = Some(tag);
+    }
+
+    fn fill(&mut self, set: usize, way: usize, tag: MemoryAddress) {
+        let ways = self.ways;
+        let arc_set = &mut self.sets[set];
+        arc_set.missing_tag = None;
+
+        let ghost = arc_set.ghost(Some(tag));
+        let recent_full = arc_set.recent_full(ghost, ways);
+        arc_set.p = arc_set.adapted_p(ghost, ways);
+        match ghost {
+            Ghost::B1(idx) => {
+                self.b1_hits += 1;
+                arc_set.b1.remove(idx);
+            }
+            Ghost::B2(idx) => {
+                self.b2_hits += 1;
+                arc_set.b2.remove(idx);
+            }
+            Ghost::None => {
+                let recency_size = arc_set.t1.len() + arc_set.b1.len();
+                let total_size = recency_size + arc_set.t2.len() + arc_set.b2.len();
+                if recency_size >= ways {
+                    if !recent_full {
+                        arc_set.b1.pop_front();
+                    }
+                } else if total_size >= 2 * ways {
+                    arc_set.b2.pop_front();
+                }
+            }
+        }
+
+        // The replaced block is remembered in the ghost list of its side
+        let in_t1 = arc_set.t1.iter().any(|&(w, _)| w == way);
+        if let Some((_, replaced_tag)) = arc_set.remove_way(way) {
+            match in_t1 {
+                true if !recent_full => arc_set.b1.push_back(replaced_tag),
+                true => {}
+                false => arc_set.b2.push_back(replaced_tag),
+            }
+        }
+
+        match ghost {
+            Ghost::None => arc_set.t1.push_back((way, tag)),
+            Ghost::B1(_) | Ghost::B2(_) => arc_set.t2.push_back((way, tag)),
+        }
+    }
+
+    fn victim(&mut self, set: usize) -> usize {
+        let arc_set = &self.sets[set];
+        let ghost = arc_set.ghost(arc_set.missing_tag);
+
+        if arc_set.recent_full(ghost, self.ways) {
+            return arc_set.t1[0].0;
+        }
+
+        let p = arc_set.adapted_p(ghost, self.ways);
+        let t1_len = arc_set.t1.len();
+        let in_b2 = matches!(ghost, Ghost::B2(_));
+        let from_t1 = t1_len >= 1 && ((in_b2 && t1_len == p) || t1_len > p);
+
+        if from_t1 || arc_set.t2.is_empty() {
+            arc_set.t1[0].0
+        } else {
+            arc_set.t2[0].0
+        }
+    }
+
+    fn invalidate(&mut self, set: usize, way: usize) {
+        self.sets[set].remove_way(way);
+    }
+
+    fn report(&self, log: &mut Logger) {
+        let (b1_hits, b2_hits) = log.ghost_hits.unwrap_or_default();
+        log.ghost_hits = Some((b1_hits + self.b1_hits, b2_hits + self.b2_hits));
+    }
+}
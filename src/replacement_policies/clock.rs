@@ -0,0 +1,54 @@
+use crate::replacement_policies::{ReplacementPolicy, ReplacementPolicyFactory};
+use crate::MemoryAddress;
+
+#[derive(Debug)]
+pub struct ClockFactory;
+
+impl ReplacementPolicyFactory for ClockFactory {
+    fn generate(&self, sets: usize, ways: usize) -> Box<dyn ReplacementPolicy> {
+        Box::new(Clock {
+            ways,
+            referenced: vec![false; sets * ways].into_boxed_slice(),
+            hands: vec![0; sets].into_boxed_slice(),
+        })
+    }
+}
+
+/// Clock (second chance) approximation of LRU, also known as NRU.
+///
+/// Every block has a reference bit, laid out in the same order as the cache
+/// blocks. The hand of a set sweeps its ways clearing the bits until it finds
+/// a block that was not referenced since the last sweep.
+pub struct Clock {
+    ways: usize,
+    referenced: Box<[bool]>,
+    hands: Box<[usize]>,
+}
+
+impl ReplacementPolicy for Clock {
+    fn touch(&mut self, set: usize, way: usize) {
+        self.referenced[set * self.ways + way] = true;
+    }
+
+    fn fill(&mut self, set: usize, way: usize, _tag: MemoryAddress) {
+        self.referenced[set * self.ways + way] = true;
+    }
+
+    fn victim(&mut self, set: usize) -> usize {
+        let start = set * self.ways;
+
+        loop {
+            let way = self.hands[set];
+            self.hands[set] = (way + 1) % self.ways;
+
+            if !self.referenced[start + way] {
+                return way;
+            }
+            self.referenced[start + way] = false;
+        }
+    }
+
+    fn invalidate(&mut self, set: usize, way: usize) {
+        self.referenced[set * self.ways + way] = false;
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::VecDeque;
+
+use crate::replacement_policies::{ReplacementPolicy, ReplacementPolicyFactory};
+use crate::MemoryAddress;
+
+#[derive(Debug)]
+pub struct FifoFactory;
+
+impl ReplacementPolicyFactory for FifoFactory {
+    fn generate(&self, sets: usize, ways: usize) -> Box<dyn ReplacementPolicy> {
+        Box::new(Fifo::new(sets, ways))
+    }
+}
+
+/// First in, first out replacement. Every set keeps its ways ordered from the
+/// oldest to the newest filled one, hits do not change the order.
+pub struct Fifo {
+    sets: Box<[VecDeque<usize>]>,
+}
+
+impl Fifo {
+    pub fn new(sets: usize, ways: usize) -> Self {
+        Self {
+            sets: vec![(0..ways).collect(); sets].into_boxed_slice(),
+        }
+    }
+
+    fn remove(&mut self, set: usize, way: usize) {
+        let nums = &mut self.sets[set];
+        let idx = nums.iter().position(|&num| num == way).unwrap();
+        nums.remove(idx);
+    }
+}
+
+impl ReplacementPolicy for Fifo {
+    fn touch(&mut self, _set: usize, _way: usize) {}
+
+    fn fill(&mut self, set: usize, way: usize, _tag: MemoryAddress) {
+        self.remove(set, way);
+        self.sets[set].push_back(way);
+    }
+
+    fn victim(&mut self, set: usize) -> usize {
+        *self.sets[set].front().unwrap()
+    }
+
+    fn invalidate(&mut self, set: usize, way: usize) {
+        self.remove(set, way);
+        self.sets[set].push_front(way);
+    }
+}
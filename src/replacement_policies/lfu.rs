@@ -0,0 +1,184 @@
+use crate::replacement_policies::{ReplacementPolicy, ReplacementPolicyFactory};
+use crate::MemoryAddress;
+
+#[derive(Debug)]
+pub struct LfuFactory {
+    /// Accesses to a set after which its counters are halved, zero to never
+    /// age them.
+    pub aging_period: u64,
+}
+
+impl ReplacementPolicyFactory for LfuFactory {
+    fn generate(&self, sets: usize, ways: usize) -> Box<dyn ReplacementPolicy> {
+        Box::new(Lfu {
+            ways,
+            aging_period: self.aging_period,
+            counts: vec![0; sets * ways].into_boxed_slice(),
+            set_accesses: vec![0; sets].into_boxed_slice(),
+        })
+    }
+}
+
+/// Least frequently used replacement.
+///
+/// Every block counts its accesses since it was placed in the cache. To let
+/// old hot blocks leave, all the counters of a set are halved every
+/// `aging_period` accesses to it.
+pub struct Lfu {
+    ways: usize,
+    aging_period: u64,
+    counts: Box<[u64]>,
+    set_accesses: Box<[u64]>,
+}
+
+impl Lfu {
+    fn age(&mut self, set: usize) {
+        if self.aging_period == 0 {
+            return;
+        }
+
+        self.set_accesses[set] += 1;
+        if self.set_accesses[set] == self.aging_period {
+            self.set_accesses[set] = 0;
+            self.counts[set * self.ways..(set + 1) * self.ways]
+                .iter_mut()
+                .for_each(|count| *count >>= 1);
+        }
+    }
+}
+
+impl ReplacementPolicy for Lfu {
+    fn touch(&mut self, set: usize, way: usize) {
+        self.age(set);
+        self.counts[set * self.ways + way] += 1;
+    }
+
+    fn fill(&mut self, set: usize, way: usize, _tag: MemoryAddress) {
+        self.age(set);
+        self.counts[set * self.ways + way] = 1;
+    }
+
+    fn victim(&mut self, set: usize) -> usize {
+        let counts = &self.counts[set * self.ways..(set + 1) * self.ways];
+
+        // Ties go to the first way, `min_by_key` already returns the first one
+        counts
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, &count)| count)
+            .map(|(way, _)| way)
+            .unwrap()
+    }
+
+    fn invalidate(&mut self, set: usize, way: usize) {
+        self.counts[set * self.ways + way] = 0;
+    }
+}
+
+#[derive(Debug)]
+pub struct LfruFactory;
+
+impl ReplacementPolicyFactory for LfruFactory {
+    fn generate(&self, sets: usize, ways: usize) -> Box<dyn ReplacementPolicy> {
+        Box::new(Lfru {
+            ways,
+            privileged_ways: ways / 2,
+            blocks: vec![LfruBlock::default(); sets * ways].into_boxed_slice(),
+            privileged_counts: vec![0; sets].into_boxed_slice(),
+            clock: 0,
+        })
+    }
+}
+
+#[derive(Clone, Default)]
+struct LfruBlock {
+    privileged: bool,
+    count: u64,
+    last_use: u64,
+}
+
+/// Least frequently recently used replacement.
+///
+/// Each set is split in a privileged partition, managed with LRU, and an
+/// unprivileged one, managed with LFU. New blocks enter the unprivileged
+/// partition and move to the privileged one when they are reused, which
+/// sends the least recently used privileged block back. Victims always come
+/// from the unprivileged partition, using recency to break frequency ties.
+pub struct Lfru {
+    ways: usize,
+    privileged_ways: usize,
+    blocks: Box<[LfruBlock]>,
+    privileged_counts: Box<[usize]>,
+    clock: u64,
+}
+
+impl Lfru {
+    fn set_blocks(&mut self, set: usize) -> &mut [LfruBlock] {
+        &mut self.blocks[set * self.ways..(set + 1) * self.ways]
+    }
+}
+
+impl ReplacementPolicy for Lfru {
+    fn touch(&mut self, set: usize, way: usize) {
+        self.clock += 1;
+        let clock = self.clock;
+        let privileged_ways = self.privileged_ways;
+        let mut privileged_count = self.privileged_counts[set];
+        let blocks = self.set_blocks(set);
+
+        blocks[way].count += 1;
+        blocks[way].last_use = clock;
+        if blocks[way].privileged {
+            return;
+        }
+
+        blocks[way].privileged = true;
+        privileged_count += 1;
+        if privileged_count > privileged_ways {
+            let demoted = blocks
+                .iter_mut()
+                .filter(|block| block.privileged)
+                .min_by_key(|block| block.last_use)
+                .unwrap();
+            demoted.privileged = false;
+            privileged_count -= 1;
+        }
+        self.privileged_counts[set] = privileged_count;
+    }
+
+    fn fill(&mut self, set: usize, way: usize, _tag: MemoryAddress) {
+        self.clock += 1;
+        let clock = self.clock;
+        let block = &mut self.blocks[set * self.ways + way];
+
+        if block.privileged {
+            self.privileged_counts[set] -= 1;
+        }
+        *block = LfruBlock {
+            privileged: false,
+            count: 1,
+            last_use: clock,
+        };
+    }
+
+    fn victim(&mut self, set: usize) -> usize {
+        // The privileged partition is at most half of the set, so there is
+        // always an unprivileged block to evict
+        self.set_blocks(set)
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| !block.privileged)
+            .min_by_key(|(_, block)| (block.count, block.last_use))
+            .map(|(way, _)| way)
+            .unwrap()
+    }
+
+    fn invalidate(&mut self, set: usize, way: usize) {
+        let block = &mut self.blocks[set * self.ways + way];
+
+        if block.privileged {
+            self.privileged_counts[set] -= 1;
+        }
+        *block = LfruBlock::default();
+    }
+}
@@ -0,0 +1,229 @@
+use crate::logger::Logger;
+use crate::replacement_policies::set_dueling::SetDueling;
+use crate::replacement_policies::{ReplacementPolicy, ReplacementPolicyFactory};
+use crate::rng::Rng;
+use crate::MemoryAddress;
+
+#[derive(Debug)]
+pub struct LruFactory;
+
+impl ReplacementPolicyFactory for LruFactory {
+    fn generate(&self, sets: usize, ways: usize) -> Box<dyn ReplacementPolicy> {
+        Box::new(Lru::new(sets, ways))
+    }
+}
+
+/// LRU insertion policy (LIP) factory.
+#[derive(Debug)]
+pub struct LipFactory;
+
+impl ReplacementPolicyFactory for LipFactory {
+    fn generate(&self, sets: usize, ways: usize) -> Box<dyn ReplacementPolicy> {
+        Box::new(Lru::with_insertion(sets, ways, Insertion::Lru))
+    }
+}
+
+/// Bimodal insertion policy (BIP) factory.
+#[derive(Debug)]
+pub struct BipFactory {
+    /// Probability of inserting a block in the MRU position.
+    pub epsilon: f64,
+    pub seed: u64,
+}
+
+impl ReplacementPolicyFactory for BipFactory {
+    fn generate(&self, sets: usize, ways: usize) -> Box<dyn ReplacementPolicy> {
+        let insertion = Insertion::Bimodal {
+            epsilon: self.epsilon,
+            rng: Rng::new(self.seed),
+        };
+        Box::new(Lru::with_insertion(sets, ways, insertion))
+    }
+}
+
+/// Dynamic insertion policy (DIP) factory.
+#[derive(Debug)]
+pub struct DipFactory {
+    /// Epsilon used by the BIP sets, see [`BipFactory`].
+    pub epsilon: f64,
+    pub seed: u64,
+}
+
+impl ReplacementPolicyFactory for DipFactory {
+    fn generate(&self, sets: usize, ways: usize) -> Box<dyn ReplacementPolicy> {
+        let insertion = Insertion::Dynamic {
+            epsilon: self.epsilon,
+            rng: Rng::new(self.seed),
+            dueling: SetDueling::new(sets, ["LRU", "BIP"]),
+        };
+        Box::new(Lru::with_insertion(sets, ways, insertion))
+    }
+}
+
+/// Position where the LRU stack receives new blocks.
+pub enum Insertion {
+    /// Most recently used position, the classic LRU.
+    Mru,
+    /// Least recently used position (LIP), so blocks that are not reused
+    /// leave before the rest.
+    Lru,
+    /// LRU position, except for a few blocks that go to the MRU one (BIP).
+    Bimodal { epsilon: f64, rng: Rng },
+    /// Set dueling between classic LRU and BIP (DIP).
+    Dynamic {
+        epsilon: f64,
+        rng: Rng,
+        dueling: SetDueling,
+    },
+}
+
+/// Marks the ends of the LRU lists.
+const NONE: usize = usize::MAX;
+
+/// Least recently used replacement. Every set keeps its ways ordered from the
+/// least to the most recently used one.
+///
+/// The order is a doubly linked list threaded through the `prev` and `next`
+/// arrays, which have an entry per block, so every operation is O(1) no matter
+/// how many ways a set has.
+///
+/// Hits always promote a block to the most recently used position, while new
+/// blocks go where the [`Insertion`] says.
+pub struct Lru {
+    ways: usize,
+    /// Least recently used block of every set.
+    heads: Box<[usize]>,
+    /// Most recently used block of every set.
+    tails: Box<[usize]>,
+    prev: Box<[usize]>,
+    next: Box<[usize]>,
+    insertion: Insertion,
+}
+
+impl Lru {
+    pub fn new(sets: usize, ways: usize) -> Self {
+        Self::with_insertion(sets, ways, Insertion::Mru)
+    }
+
+    pub fn with_insertion(sets: usize, ways: usize, insertion: Insertion) -> Self {
+        let blocks = sets * ways;
+        let mut lru = Self {
+            ways,
+            heads: vec![NONE; sets].into_boxed_slice(),
+            tails: vec![NONE; sets].into_boxed_slice(),
+            prev: vec![NONE; blocks].into_boxed_slice(),
+            next: vec![NONE; blocks].into_boxed_slice(),
+            insertion,
+        };
+
+        for block in 0..blocks {
+            lru.push_back(block / ways, block);
+        }
+
+        lru
+    }
+
+    pub fn mark_use(&mut self, set: usize, way: usize) {
+        let block = set * self.ways + way;
+        self.unlink(set, block);
+        self.push_back(set, block);
+    }
+
+    pub fn mark_unused(&mut self, set: usize, way: usize) {
+        let block = set * self.ways + way;
+        self.unlink(set, block);
+        self.push_front(set, block);
+    }
+
+    pub fn get_lru(&self, set: usize) -> usize {
+        self.heads[set] - set * self.ways
+    }
+
+    fn unlink(&mut self, set: usize, block: usize) {
+        let (prev, next) = (self.prev[block], self.next[block]);
+
+        match prev {
+            NONE => self.heads[set] = next,
+            prev => self.next[prev] = next,
+        }
+        match next {
+            NONE => self.tails[set] = prev,
+            next => self.prev[next] = prev,
+        }
+    }
+
+    fn push_back(&mut self, set: usize, block: usize) {
+        let tail = self.tails[set];
+
+        self.prev[block] = tail;
+        self.next[block] = NONE;
+        match tail {
+            NONE => self.heads[set] = block,
+            tail => self.next[tail] = block,
+        }
+        self.tails[set] = block;
+    }
+
+    fn push_front(&mut self, set: usize, block: usize) {
+        let head = self.heads[set];
+
+        self.prev[block] = NONE;
+        self.next[block] = head;
+        match head {
+            NONE => self.tails[set] = block,
+            head => self.prev[head] = block,
+        }
+        self.heads[set] = block;
+    }
+
+    fn insert_at_mru(&mut self, set: usize) -> bool {
+        match &mut self.insertion {
+            Insertion::Mru => true,
+            Insertion::Lru => false,
+            Insertion::Bimodal { epsilon, rng } => rng.chance(*epsilon),
+            Insertion::Dynamic {
+                epsilon,
+                rng,
+                dueling,
+            } => {
+                dueling.miss(set);
+                !dueling.uses_b(set) || rng.chance(*epsilon)
+            }
+        }
+    }
+}
+
+impl ReplacementPolicy for Lru {
+    fn touch(&mut self, set: usize, way: usize) {
+        if let Insertion::Dynamic { dueling, .. } = &mut self.insertion {
+            dueling.access();
+        }
+        self.mark_use(set, way);
+    }
+
+    fn fill(&mut self, set: usize, way: usize, _tag: MemoryAddress) {
+        if let Insertion::Dynamic { dueling, .. } = &mut self.insertion {
+            dueling.access();
+        }
+
+        if self.insert_at_mru(set) {
+            self.mark_use(set, way);
+        } else {
+            self.mark_unused(set, way);
+        }
+    }
+
+    fn victim(&mut self, set: usize) -> usize {
+        self.get_lru(set)
+    }
+
+    fn invalidate(&mut self, set: usize, way: usize) {
+        self.mark_unused(set, way);
+    }
+
+    fn report(&self, log: &mut Logger) {
+        if let Insertion::Dynamic { dueling, .. } = &self.insertion {
+            dueling.report(log);
+        }
+    }
+}
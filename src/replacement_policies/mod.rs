@@ -0,0 +1,65 @@
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::logger::Logger;
+use crate::replacement_policies::opt::NextUses;
+use crate::MemoryAddress;
+
+pub mod arc;
+pub mod clock;
+pub mod fifo;
+pub mod lfu;
+pub mod lru;
+pub mod opt;
+pub mod plru;
+pub mod random;
+pub mod rrip;
+pub mod set_dueling;
+
+/// A factory trait for creating replacement policies.
+pub trait ReplacementPolicyFactory: fmt::Debug {
+    /// Generates a new replacement policy instance that manages `sets` sets
+    /// of `ways` blocks each.
+    fn generate(&self, sets: usize, ways: usize) -> Box<dyn ReplacementPolicy>;
+
+    /// Checks that the generated policies can manage sets of `ways` blocks.
+    fn check_ways(&self, _ways: usize) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// Returns the next uses the generated policies read, if they need to
+    /// know the future of the trace.
+    fn lookahead(&self) -> Option<Rc<NextUses>> {
+        None
+    }
+}
+
+/// Defines the behavior of a common replacement policy.
+///
+/// A single instance keeps the replacement state of every set of a cache.
+/// Ways are always given relative to the start of their set.
+pub trait ReplacementPolicy {
+    /// Updates the state after a hit on the block stored in `way`.
+    fn touch(&mut self, set: usize, way: usize);
+
+    /// Called on a miss for the block with `tag`, before choosing where to
+    /// place it.
+    fn miss(&mut self, _set: usize, _tag: MemoryAddress) {}
+
+    /// Updates the state after the block with `tag` is placed in `way`,
+    /// replacing the block that was there if it was valid.
+    fn fill(&mut self, set: usize, way: usize, tag: MemoryAddress);
+
+    /// Chooses the way of `set` whose block should be evicted.
+    ///
+    /// This function is only called when every block of the set is valid, so
+    /// the policy does not need to care about empty ways.
+    fn victim(&mut self, set: usize) -> usize;
+
+    /// Updates the state after the block stored in `way` stops being valid.
+    fn invalidate(&mut self, set: usize, way: usize);
+
+    /// Adds the statistics collected by the policy to the log.
+    fn report(&self, _log: &mut Logger) {}
+}
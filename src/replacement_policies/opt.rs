@@ -0,0 +1,123 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::replacement_policies::{ReplacementPolicy, ReplacementPolicyFactory};
+use crate::MemoryAddress;
+
+/// Position of the next access to the same block for every access of a trace.
+///
+/// The simulator builds it with a pass over the trace before running it and
+/// moves the cursor after every access, so OPT policies know how far in the
+/// future the block being accessed is needed again.
+#[derive(Default)]
+pub struct NextUses {
+    next_uses: RefCell<Vec<usize>>,
+    cursor: Cell<usize>,
+}
+
+impl NextUses {
+    /// Value used for blocks that are never accessed again.
+    pub const NEVER: usize = usize::MAX;
+
+    /// Computes the next uses of a sequence of accesses.
+    ///
+    /// Each access is given as the cache it goes to and the address of the
+    /// block it touches. Accesses only count as uses of blocks of the same
+    /// cache.
+    pub fn build(&self, accesses: impl IntoIterator<Item = (usize, MemoryAddress)>) {
+        let accesses: Vec<_> = accesses.into_iter().collect();
+        let mut next_uses = vec![Self::NEVER; accesses.len()];
+        let mut last_seen = HashMap::new();
+
+        for (idx, access) in accesses.into_iter().enumerate().rev() {
+            if let Some(next) = last_seen.insert(access, idx) {
+                next_uses[idx] = next;
+            }
+        }
+
+        self.next_uses.replace(next_uses);
+        self.cursor.set(0);
+    }
+
+    /// Moves to the next access of the trace.
+    pub fn advance(&self) {
+        self.cursor.set(self.cursor.get() + 1);
+    }
+
+    /// Returns when the block of the current access is used again.
+    pub fn current(&self) -> usize {
+        self.next_uses
+            .borrow()
+            .get(self.cursor.get())
+            .copied()
+            .unwrap_or(Self::NEVER)
+    }
+}
+
+impl fmt::Debug for NextUses {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NextUses")
+            .field("accesses", &self.next_uses.borrow().len())
+            .finish()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct OptFactory {
+    pub next_uses: Rc<NextUses>,
+}
+
+impl ReplacementPolicyFactory for OptFactory {
+    fn generate(&self, sets: usize, ways: usize) -> Box<dyn ReplacementPolicy> {
+        Box::new(Opt {
+            ways,
+            next_uses: Rc::clone(&self.next_uses),
+            blocks_next_use: vec![NextUses::NEVER; sets * ways].into_boxed_slice(),
+        })
+    }
+
+    fn lookahead(&self) -> Option<Rc<NextUses>> {
+        Some(Rc::clone(&self.next_uses))
+    }
+}
+
+/// Belady's optimal replacement (OPT or MIN). Evicts the block whose next
+/// use is the furthest in the future.
+///
+/// The next uses come from the trace, so the policy is only optimal for
+/// caches that receive the accesses of the trace directly.
+pub struct Opt {
+    ways: usize,
+    next_uses: Rc<NextUses>,
+    blocks_next_use: Box<[usize]>,
+}
+
+impl ReplacementPolicy for Opt {
+    fn touch(&mut self, set: usize, way: usize) {
+        self.blocks_next_use[set * self.ways + way] = self.next_uses.current();
+    }
+
+    fn fill(&mut self, set: usize, way: usize, _tag: MemoryAddress) {
+        self.blocks_next_use[set * self.ways + way] = self.next_uses.current();
+    }
+
+    fn victim(&mut self, set: usize) -> usize {
+        let start = set * self.ways;
+        let blocks_next_use = &self.blocks_next_use[start..start + self.ways];
+
+        // Ties go to the first way, `max_by_key` would return the last one
+        blocks_next_use
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, &next_use)| next_use)
+            .map(|(way, _)| way)
+            .unwrap()
+    }
+
+    fn invalidate(&mut self, set: usize, way: usize) {
+        self.blocks_next_use[set * self.ways + way] = NextUses::NEVER;
+    }
+}
@@ -0,0 +1,196 @@
+use crate::replacement_policies::{ReplacementPolicy, ReplacementPolicyFactory};
+use crate::MemoryAddress;
+use std::error::Error;
+
+/// Fixed amount of bits per set, packed in 64 bit words.
+struct SetBits {
+    words_per_set: usize,
+    words: Box<[u64]>,
+}
+
+impl SetBits {
+    fn new(sets: usize, bits_per_set: usize) -> Self {
+        let words_per_set = bits_per_set.div_ceil(64);
+        Self {
+            words_per_set,
+            words: vec![0; sets * words_per_set].into_boxed_slice(),
+        }
+    }
+
+    fn get(&self, set: usize, bit: usize) -> bool {
+        self.words[set * self.words_per_set + bit / 64] >> (bit % 64) & 1 == 1
+    }
+
+    fn set(&mut self, set: usize, bit: usize, value: bool) {
+        let word = &mut self.words[set * self.words_per_set + bit / 64];
+        if value {
+            *word |= 1 << (bit % 64);
+        } else {
+            *word &= !(1 << (bit % 64));
+        }
+    }
+
+    fn set_words(&self, set: usize) -> &[u64] {
+        let start = set * self.words_per_set;
+        &self.words[start..start + self.words_per_set]
+    }
+
+    fn clear(&mut self, set: usize) {
+        let start = set * self.words_per_set;
+        self.words[start..start + self.words_per_set].fill(0);
+    }
+
+    fn count_ones(&self, set: usize) -> usize {
+        self.set_words(set)
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    fn first_zero(&self, set: usize) -> usize {
+        let mut bit = 0;
+        for word in self.set_words(set) {
+            if *word != u64::MAX {
+                return bit + word.trailing_ones() as usize;
+            }
+            bit += 64;
+        }
+
+        bit
+    }
+}
+
+#[derive(Debug)]
+pub struct TreePlruFactory;
+
+impl ReplacementPolicyFactory for TreePlruFactory {
+    fn check_ways(&self, ways: usize) -> Result<(), Box<dyn Error>> {
+        if ways.is_power_of_two() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Tree PLRU needs a power of two number of ways, got {}",
+                ways
+            )
+            .into())
+        }
+    }
+
+    fn generate(&self, sets: usize, ways: usize) -> Box<dyn ReplacementPolicy> {
+        assert!(
+            ways.is_power_of_two(),
+            "Tree PLRU requires a power of two number of ways, got {}",
+            ways
+        );
+
+        Box::new(TreePlru {
+            depth: ways.ilog2(),
+            nodes: SetBits::new(sets, ways - 1),
+        })
+    }
+}
+
+/// Tree pseudo-LRU replacement.
+///
+/// Each set is a complete binary tree with a way on every leaf. The `ways - 1`
+/// inner nodes are stored in heap order and each one points to the half of
+/// the tree that holds the next victim (`false` left, `true` right).
+pub struct TreePlru {
+    depth: u32,
+    nodes: SetBits,
+}
+
+impl TreePlru {
+    /// Makes every node in the path to `way` point away from it, or towards
+    /// it when `towards` is set.
+    fn update_path(&mut self, set: usize, way: usize, towards: bool) {
+        let mut node = 0;
+        for level in (0..self.depth).rev() {
+            let right = (way >> level) & 1 == 1;
+            self.nodes.set(set, node, right == towards);
+            node = 2 * node + 1 + right as usize;
+        }
+    }
+}
+
+impl ReplacementPolicy for TreePlru {
+    fn touch(&mut self, set: usize, way: usize) {
+        self.update_path(set, way, false);
+    }
+
+    fn fill(&mut self, set: usize, way: usize, _tag: MemoryAddress) {
+        self.update_path(set, way, false);
+    }
+
+    fn victim(&mut self, set: usize) -> usize {
+        let mut node = 0;
+        let mut way = 0;
+        for _ in 0..self.depth {
+            let right = self.nodes.get(set, node);
+            way = (way << 1) | right as usize;
+            node = 2 * node + 1 + right as usize;
+        }
+
+        way
+    }
+
+    fn invalidate(&mut self, set: usize, way: usize) {
+        self.update_path(set, way, true);
+    }
+}
+
+#[derive(Debug)]
+pub struct BitPlruFactory;
+
+impl ReplacementPolicyFactory for BitPlruFactory {
+    fn generate(&self, sets: usize, ways: usize) -> Box<dyn ReplacementPolicy> {
+        Box::new(BitPlru {
+            ways,
+            mru: SetBits::new(sets, ways),
+        })
+    }
+}
+
+/// MRU-bit pseudo-LRU replacement.
+///
+/// Every block has a bit that is set when it is used. When the last clear bit
+/// of a set would be set, all the other bits of the set are cleared. The
+/// victim is the first way whose bit is clear.
+pub struct BitPlru {
+    ways: usize,
+    mru: SetBits,
+}
+
+impl BitPlru {
+    fn mark_use(&mut self, set: usize, way: usize) {
+        self.mru.set(set, way, true);
+
+        if self.ways > 1 && self.mru.count_ones(set) == self.ways {
+            self.mru.clear(set);
+            self.mru.set(set, way, true);
+        }
+    }
+}
+
+impl ReplacementPolicy for BitPlru {
+    fn touch(&mut self, set: usize, way: usize) {
+        self.mark_use(set, way);
+    }
+
+    fn fill(&mut self, set: usize, way: usize, _tag: MemoryAddress) {
+        self.mark_use(set, way);
+    }
+
+    fn victim(&mut self, set: usize) -> usize {
+        // The bit of a single way is never cleared, and the way is the only
+        // choice anyway
+        if self.ways == 1 {
+            return 0;
+        }
+        self.mru.first_zero(set)
+    }
+
+    fn invalidate(&mut self, set: usize, way: usize) {
+        self.mru.set(set, way, false);
+    }
+}
@@ -0,0 +1,35 @@
+use crate::replacement_policies::{ReplacementPolicy, ReplacementPolicyFactory};
+use crate::rng::Rng;
+use crate::MemoryAddress;
+
+#[derive(Debug)]
+pub struct RandomFactory {
+    pub seed: u64,
+}
+
+impl ReplacementPolicyFactory for RandomFactory {
+    fn generate(&self, _sets: usize, ways: usize) -> Box<dyn ReplacementPolicy> {
+        Box::new(Random {
+            ways,
+            rng: Rng::new(self.seed),
+        })
+    }
+}
+
+/// Evicts a pseudo-random way. Runs with the same seed evict the same blocks.
+pub struct Random {
+    ways: usize,
+    rng: Rng,
+}
+
+impl ReplacementPolicy for Random {
+    fn touch(&mut self, _set: usize, _way: usize) {}
+
+    fn fill(&mut self, _set: usize, _way: usize, _tag: MemoryAddress) {}
+
+    fn victim(&mut self, _set: usize) -> usize {
+        self.rng.below(self.ways)
+    }
+
+    fn invalidate(&mut self, _set: usize, _way: usize) {}
+}
@@ -0,0 +1,168 @@
+use crate::logger::Logger;
+use crate::replacement_policies::set_dueling::SetDueling;
+use crate::replacement_policies::{ReplacementPolicy, ReplacementPolicyFactory};
+use crate::rng::Rng;
+use crate::MemoryAddress;
+
+#[derive(Debug)]
+pub struct SrripFactory {
+    pub rrpv_bits: u32,
+}
+
+impl ReplacementPolicyFactory for SrripFactory {
+    fn generate(&self, sets: usize, ways: usize) -> Box<dyn ReplacementPolicy> {
+        Box::new(Rrip::new(sets, ways, self.rrpv_bits, RripMode::Static))
+    }
+}
+
+#[derive(Debug)]
+pub struct BrripFactory {
+    pub rrpv_bits: u32,
+    /// Probability of inserting a block with a long re-reference interval
+    /// instead of a distant one.
+    pub insertion_probability: f64,
+    pub seed: u64,
+}
+
+impl ReplacementPolicyFactory for BrripFactory {
+    fn generate(&self, sets: usize, ways: usize) -> Box<dyn ReplacementPolicy> {
+        let mode = RripMode::Bimodal {
+            insertion_probability: self.insertion_probability,
+            rng: Rng::new(self.seed),
+        };
+        Box::new(Rrip::new(sets, ways, self.rrpv_bits, mode))
+    }
+}
+
+#[derive(Debug)]
+pub struct DrripFactory {
+    pub rrpv_bits: u32,
+    /// Insertion probability used by the BRRIP sets, see [`BrripFactory`].
+    pub insertion_probability: f64,
+    pub seed: u64,
+}
+
+impl ReplacementPolicyFactory for DrripFactory {
+    fn generate(&self, sets: usize, ways: usize) -> Box<dyn ReplacementPolicy> {
+        let mode = RripMode::Dynamic {
+            insertion_probability: self.insertion_probability,
+            rng: Rng::new(self.seed),
+            dueling: SetDueling::new(sets, ["SRRIP", "BRRIP"]),
+        };
+        Box::new(Rrip::new(sets, ways, self.rrpv_bits, mode))
+    }
+}
+
+enum RripMode {
+    /// SRRIP, every block is inserted with a long re-reference interval.
+    Static,
+    /// BRRIP, most blocks are inserted with a distant re-reference interval.
+    Bimodal {
+        insertion_probability: f64,
+        rng: Rng,
+    },
+    /// DRRIP, set dueling between SRRIP and BRRIP.
+    Dynamic {
+        insertion_probability: f64,
+        rng: Rng,
+        dueling: SetDueling,
+    },
+}
+
+/// Re-reference interval prediction replacement.
+///
+/// Every block has a re-reference prediction value (RRPV). Hits predict a
+/// near-immediate re-reference (RRPV 0) and the victim is the first block
+/// predicted to be re-referenced in the distant future (maximum RRPV).
+pub struct Rrip {
+    ways: usize,
+    max_rrpv: u8,
+    rrpvs: Box<[u8]>,
+    mode: RripMode,
+}
+
+impl Rrip {
+    fn new(sets: usize, ways: usize, rrpv_bits: u32, mode: RripMode) -> Self {
+        assert!(
+            (1..=8).contains(&rrpv_bits),
+            "RRPV width must be between 1 and 8 bits, got {}",
+            rrpv_bits
+        );
+
+        let max_rrpv = ((1u16 << rrpv_bits) - 1) as u8;
+        Self {
+            ways,
+            max_rrpv,
+            rrpvs: vec![max_rrpv; sets * ways].into_boxed_slice(),
+            mode,
+        }
+    }
+
+    fn insertion_rrpv(&mut self, set: usize) -> u8 {
+        let long = self.max_rrpv.saturating_sub(1);
+        let distant = self.max_rrpv;
+
+        match &mut self.mode {
+            RripMode::Static => long,
+            RripMode::Bimodal {
+                insertion_probability,
+                rng,
+            } => {
+                if rng.chance(*insertion_probability) {
+                    long
+                } else {
+                    distant
+                }
+            }
+            RripMode::Dynamic {
+                insertion_probability,
+                rng,
+                dueling,
+            } => {
+                dueling.miss(set);
+                if dueling.uses_b(set) && !rng.chance(*insertion_probability) {
+                    distant
+                } else {
+                    long
+                }
+            }
+        }
+    }
+}
+
+impl ReplacementPolicy for Rrip {
+    fn touch(&mut self, set: usize, way: usize) {
+        if let RripMode::Dynamic { dueling, .. } = &mut self.mode {
+            dueling.access();
+        }
+        self.rrpvs[set * self.ways + way] = 0;
+    }
+
+    fn fill(&mut self, set: usize, way: usize, _tag: MemoryAddress) {
+        if let RripMode::Dynamic { dueling, .. } = &mut self.mode {
+            dueling.access();
+        }
+        self.rrpvs[set * self.ways + way] = self.insertion_rrpv(set);
+    }
+
+    fn victim(&mut self, set: usize) -> usize {
+        let rrpvs = &mut self.rrpvs[set * self.ways..(set + 1) * self.ways];
+
+        loop {
+            if let Some(way) = rrpvs.iter().position(|&rrpv| rrpv == self.max_rrpv) {
+                return way;
+            }
+            rrpvs.iter_mut().for_each(|rrpv| *rrpv += 1);
+        }
+    }
+
+    fn invalidate(&mut self, set: usize, way: usize) {
+        self.rrpvs[set * self.ways + way] = self.max_rrpv;
+    }
+
+    fn report(&self, log: &mut Logger) {
+        if let RripMode::Dynamic { dueling, .. } = &self.mode {
+            dueling.report(log);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use crate::logger::Logger;
+
+/// Number of leader sets dedicated to each of the competing policies.
+const LEADER_SETS: usize = 32;
+/// Bits of the policy selection counter.
+const PSEL_BITS: u32 = 10;
+
+/// Role of a set in a set dueling monitor.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SetRole {
+    /// The set always uses the first policy.
+    LeaderA,
+    /// The set always uses the second policy.
+    LeaderB,
+    /// The set uses the policy that is currently winning.
+    Follower,
+}
+
+/// Chooses between two policies by dedicating a few leader sets to each one
+/// and counting their misses in a saturating PSEL counter.
+///
+/// Leader sets are spread through the cache: one of every `period` sets leads
+/// each policy, with at most 32 leaders per policy. A cache too small to
+/// have leaders of both policies and followers does not duel, and every set
+/// uses the first policy.
+pub struct SetDueling {
+    names: [&'static str; 2],
+    period: usize,
+    /// Whether the cache has leaders of both policies and followers.
+    dueling: bool,
+    psel: u32,
+    use_b: bool,
+    accesses: u128,
+    /// Access number at which each policy became the one used by followers.
+    pub timeline: Vec<(u128, &'static str)>,
+}
+
+impl SetDueling {
+    pub fn new(sets: usize, names: [&'static str; 2]) -> Self {
+        let mut dueling = Self {
+            names,
+            period: (sets / LEADER_SETS).max(4),
+            dueling: false,
+            psel: 1 << (PSEL_BITS - 1),
+            use_b: false,
+            accesses: 0,
+            timeline: Vec::new(),
+        };
+
+        let roles: Vec<_> = (0..sets).map(|set| dueling.role(set)).collect();
+        dueling.dueling = roles.contains(&SetRole::LeaderB) && roles.contains(&SetRole::Follower);
+        if dueling.dueling {
+            dueling.timeline.push((0, names[0]));
+        }
+        dueling
+    }
+
+    pub fn role(&self, set: usize) -> SetRole {
+        match set % self.period {
+            0 => SetRole::LeaderA,
+            offset if offset == self.period / 2 => SetRole::LeaderB,
+            _ => SetRole::Follower,
+        }
+    }
+
+    /// Returns whether `set` must use the second policy.
+    pub fn uses_b(&self, set: usize) -> bool {
+        if !self.dueling {
+            return false;
+        }
+        match self.role(set) {
+            SetRole::LeaderA => false,
+            SetRole::LeaderB => true,
+            SetRole::Follower => self.use_b,
+        }
+    }
+
+    /// Counts an access to the cache.
+    pub fn access(&mut self) {
+        self.accesses += 1;
+    }
+
+    /// Counts a miss in `set`, updating the counter if it is a leader.
+    pub fn miss(&mut self, set: usize) {
+        if !self.dueling {
+            return;
+        }
+        match self.role(set) {
+            SetRole::LeaderA => self.psel = (self.psel + 1).min((1 << PSEL_BITS) - 1),
+            SetRole::LeaderB => self.psel = self.psel.saturating_sub(1),
+            SetRole::Follower => return,
+        }
+
+        // The counter starts at the midpoint, where followers use A
+        let use_b = self.psel > 1 << (PSEL_BITS - 1);
+        if use_b != self.use_b {
+            self.use_b = use_b;
+            self.timeline
+                .push((self.accesses, self.names[use_b as usize]));
+        }
+    }
+
+    /// Adds the policies the followers used over time to the log, or the
+    /// policy of every set if the cache does not duel.
+    pub fn report(&self, log: &mut Logger) {
+        if self.dueling {
+            log.dueling_winners.extend_from_slice(&self.timeline);
+        } else {
+            log.dueling_fallback = Some(self.names[0]);
+        }
+    }
+}
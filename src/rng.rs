@@ -0,0 +1,39 @@
+const SEED_MIX: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Small xorshift64* pseudo-random number generator.
+///
+/// The simulator only needs reproducible sequences for a given seed, not
+/// cryptographic quality, so this avoids pulling an external crate.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // A zero state would make the generator return zero forever, so the
+        // one seed that leads to it takes the state of seed 0 instead
+        let state = match seed ^ SEED_MIX {
+            0 => SEED_MIX,
+            state => state,
+        };
+        Self { state }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a number in the range `0..bound`.
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// Returns `true` with the given probability.
+    pub fn chance(&mut self, probability: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+}
@@ -0,0 +1,525 @@
+use crate::binary_trace::BinaryDecoder;
+use crate::cache::AccessType::{self, Read, Write};
+use crate::cache::ValueType::{Data, Instruction};
+use crate::cli_parser::ParsedArgs;
+use crate::{MemoryAddress, WORD_SIZE};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::Display;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Header that declares the threads of a trace.
+const THREADS_HEADER: &str = "#threads";
+
+/// Bytes an access of a text trace can touch.
+pub const ACCESS_SIZES: [usize; 6] = [1, 2, 4, 8, 16, 64];
+
+/// An access of a trace, with the thread that makes it.
+pub type ThreadAccess = (usize, AccessType, MemoryAddress);
+
+/// Format a trace is written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// `<operation> <hexadecimal address> <size>`, with an optional
+    /// `#threads` header.
+    Text,
+    /// Dinero IV `din` records, `<label> <hexadecimal address>`.
+    Din,
+    /// Output of `valgrind --tool=lackey --trace-mem=yes`, lines of
+    /// `I  <address>,<size>` for instructions and ` L`, ` S` or ` M` for
+    /// loads, stores and modifies.
+    Lackey,
+    /// Compact binary records, written by `trace-convert`, see
+    /// [`crate::binary_trace::BinaryEncoder`].
+    Binary,
+}
+
+impl TraceFormat {
+    /// Returns the format of the trace at `path` from its extension, which is
+    /// text for any extension without a format of its own.
+    pub fn detect(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("din") => TraceFormat::Din,
+            Some("lackey") => TraceFormat::Lackey,
+            Some("bin") => TraceFormat::Binary,
+            _ => TraceFormat::Text,
+        }
+    }
+}
+
+impl FromStr for TraceFormat {
+    type Err = Box<dyn Error>;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "text" => Ok(TraceFormat::Text),
+            "din" => Ok(TraceFormat::Din),
+            "lackey" => Ok(TraceFormat::Lackey),
+            "binary" => Ok(TraceFormat::Binary),
+            _ => Err(format!("Invalid trace format: {}", format).into()),
+        }
+    }
+}
+
+impl Display for TraceFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceFormat::Text => write!(f, "text"),
+            TraceFormat::Din => write!(f, "din"),
+            TraceFormat::Lackey => write!(f, "lackey"),
+            TraceFormat::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+/// A record of a trace.
+#[derive(Clone, Copy, Debug)]
+pub enum TraceRecord {
+    Access(ThreadAccess),
+    /// Empties every cache, writing back the dirty blocks.
+    Flush,
+}
+
+/// A record of a trace as it is written, before its accesses are checked,
+/// rescaled and split in blocks.
+#[derive(Clone, Copy, Debug)]
+pub enum RawRecord {
+    /// An access with the thread that makes it.
+    Access(usize, SizedAccess),
+    Flush,
+}
+
+/// A line of a trace.
+enum Line {
+    Access(usize, SizedAccess),
+    /// A read followed by a write of the same bytes.
+    Modify(MemoryAddress, Option<usize>),
+    Flush,
+    /// A record that is not an access, which is skipped.
+    Escape,
+}
+
+/// An access as written in a trace, with the bytes it touches if the trace
+/// gives them.
+pub type SizedAccess = (AccessType, MemoryAddress, Option<usize>);
+
+/// Reads the accesses of a trace, written one per line as
+/// `<operation> <hexadecimal address>`.
+///
+/// The operation is `0` for data reads, `1` for writes and `2` for
+/// instruction reads. The address can be followed by the bytes the access
+/// touches, as `<operation> <hexadecimal address> <size>`, and an access
+/// without size touches the word of its address. Anything else after the
+/// address is ignored.
+///
+/// Traces can also be in the Dinero IV `din` format, come from valgrind's
+/// Lackey tool or be binary, see [`TraceFormat`].
+///
+/// Addresses are in bytes, with words of `word_size` bytes. They are moved to
+/// the same word in words of [`WORD_SIZE`] bytes, which the caches work with,
+/// and an access that touches several blocks is split in one access per
+/// block.
+///
+/// A trace of several threads starts with a `#threads <count>` header, and
+/// its lines start with the thread that makes the access, as
+/// `<thread> <operation> <hexadecimal address>`.
+pub struct TraceReader<R> {
+    reader: R,
+    format: TraceFormat,
+    /// Threads declared by the header, once the first line was read.
+    threads: Option<Option<usize>>,
+    /// First access, read while looking for the header.
+    first_line: Option<String>,
+    /// Words of the blocks the accesses are split in, if they are split.
+    block_size: Option<usize>,
+    word_size: usize,
+    address_width: Option<u32>,
+    /// Accesses left of the last access that was split.
+    pieces: VecDeque<ThreadAccess>,
+    /// Write of the last modify, which follows its read.
+    modify_write: Option<RawRecord>,
+    /// Decoder of a binary trace, once its header was read.
+    binary: Option<BinaryDecoder>,
+}
+
+impl TraceReader<BufReader<File>> {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let mut reader = Self::new(BufReader::new(file));
+        reader.set_format(TraceFormat::detect(path));
+        Ok(reader)
+    }
+
+    /// Opens the trace at `path` for the caches `args` describes, splitting
+    /// its accesses in their blocks.
+    pub fn open_with(path: &Path, args: &ParsedArgs) -> io::Result<Self> {
+        let mut reader = Self::open(path)?;
+        if let Some(format) = args.trace_format {
+            reader.set_format(format);
+        }
+        reader.set_block_size(args.block_size);
+        reader.set_word_size(args.word_size);
+        reader.set_address_width(args.address_width);
+        Ok(reader)
+    }
+}
+
+impl<R: BufRead> TraceReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            format: TraceFormat::Text,
+            threads: None,
+            first_line: None,
+            block_size: None,
+            word_size: WORD_SIZE,
+            address_width: None,
+            pieces: VecDeque::new(),
+            modify_write: None,
+            binary: None,
+        }
+    }
+
+    pub fn set_format(&mut self, format: TraceFormat) {
+        self.format = format;
+    }
+
+    /// Splits the accesses that touch several blocks of `block_size` words.
+    pub fn set_block_size(&mut self, block_size: usize) {
+        self.block_size = Some(block_size);
+    }
+
+    pub fn set_word_size(&mut self, word_size: usize) {
+        self.word_size = word_size;
+    }
+
+    /// Checks that every address of the trace fits in `address_width` bits,
+    /// if there is a width.
+    pub fn set_address_width(&mut self, address_width: Option<u32>) {
+        self.address_width = address_width;
+    }
+
+    /// Returns the threads the header of the trace declares, or `None` if it
+    /// has no header.
+    pub fn threads(&mut self) -> Result<Option<usize>, Box<dyn Error>> {
+        if let Some(threads) = self.threads {
+            return Ok(threads);
+        }
+
+        let threads = match self.format {
+            TraceFormat::Text => self.text_threads()?,
+            TraceFormat::Binary => {
+                let (decoder, threads) = BinaryDecoder::read_header(&mut self.reader)?;
+                self.binary = Some(decoder);
+                threads
+            }
+            TraceFormat::Din | TraceFormat::Lackey => None,
+        };
+        if threads == Some(0) {
+            return Err("A trace needs at least one thread".into());
+        }
+
+        self.threads = Some(threads);
+        Ok(threads)
+    }
+
+    /// Reads the `#threads` header of a text trace, keeping the first access
+    /// if there is none.
+    fn text_threads(&mut self) -> Result<Option<usize>, Box<dyn Error>> {
+        Ok(match self.next_line().transpose()? {
+            Some(line) => match line.strip_prefix(THREADS_HEADER) {
+                Some(count) => Some(count.trim().parse::<usize>()?),
+                None => {
+                    self.first_line = Some(line);
+                    None
+                }
+            },
+            None => None,
+        })
+    }
+
+    /// Returns the next access with the thread that makes it, which is
+    /// always `0` in traces without threads.
+    ///
+    /// Flush records are skipped.
+    pub fn next_access(&mut self) -> Option<Result<ThreadAccess, Box<dyn Error>>> {
+        loop {
+            match self.next_record()? {
+                Ok(TraceRecord::Access(access)) => return Some(Ok(access)),
+                Ok(TraceRecord::Flush) => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+
+    /// Returns the next record of the trace.
+    pub fn next_record(&mut self) -> Option<Result<TraceRecord, Box<dyn Error>>> {
+        loop {
+            if let Some(piece) = self.pieces.pop_front() {
+                return Some(Ok(TraceRecord::Access(piece)));
+            }
+
+            let split = match self.next_raw_record()? {
+                Ok(RawRecord::Access(thread, access)) => self.split(thread, access),
+                Ok(RawRecord::Flush) => return Some(Ok(TraceRecord::Flush)),
+                Err(err) => Err(err),
+            };
+            if let Err(err) = split {
+                return Some(Err(err));
+            }
+        }
+    }
+
+    /// Returns the next record as the trace writes it. Modifies are returned
+    /// as their read followed by their write, and escapes are skipped.
+    pub fn next_raw_record(&mut self) -> Option<Result<RawRecord, Box<dyn Error>>> {
+        if let Some(write) = self.modify_write.take() {
+            return Some(Ok(write));
+        }
+        let threads = match self.threads() {
+            Ok(threads) => threads,
+            Err(err) => return Some(Err(err)),
+        };
+        if let Some(decoder) = self.binary.as_mut() {
+            return decoder.read_record(&mut self.reader);
+        }
+
+        loop {
+            let line = match self
+                .first_line
+                .take()
+                .map(Ok)
+                .or_else(|| self.next_line())?
+            {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+
+            let line = match (self.format, threads) {
+                (TraceFormat::Text, Some(threads)) => parse_thread_line(&line, threads),
+                (TraceFormat::Din, _) => parse_din_line(&line),
+                (TraceFormat::Lackey, _) => parse_lackey_line(&line),
+                _ => parse_line(&line).map(|access| Line::Access(0, access)),
+            };
+            return Some(match line {
+                Ok(Line::Access(thread, access)) => Ok(RawRecord::Access(thread, access)),
+                Ok(Line::Modify(address, size)) => {
+                    self.modify_write = Some(RawRecord::Access(0, (Write, address, size)));
+                    Ok(RawRecord::Access(0, (Read(Data), address, size)))
+                }
+                Ok(Line::Flush) => Ok(RawRecord::Flush),
+                Ok(Line::Escape) => continue,
+                Err(err) => Err(err),
+            });
+        }
+    }
+
+    /// Queues the accesses to every block `access` of `thread` touches.
+    fn split(&mut self, thread: usize, access: SizedAccess) -> Result<(), Box<dyn Error>> {
+        let (access_type, _, _) = access;
+        let addresses = self.block_addresses(access, self.block_size)?;
+        self.pieces.extend(
+            addresses
+                .into_iter()
+                .map(|address| (thread, access_type, address)),
+        );
+        Ok(())
+    }
+
+    /// Returns the address the caches see for every block of `block_size`
+    /// words that `access` touches, or only for its first block if there is
+    /// no block size.
+    pub fn block_addresses(
+        &self,
+        access: SizedAccess,
+        block_size: Option<usize>,
+    ) -> Result<Vec<MemoryAddress>, Box<dyn Error>> {
+        let (_, address, size) = access;
+        let last = match size {
+            Some(size) => address
+                .checked_add(size as MemoryAddress - 1)
+                .ok_or_else(|| format!("Access to {:#x} goes past the last address", address))?,
+            None => address,
+        };
+        check_address(last, self.address_width)?;
+
+        let mut addresses = Vec::new();
+        let mut piece = address;
+        loop {
+            addresses.push(self.rescale(piece)?);
+
+            let Some(block_size) = block_size else {
+                return Ok(addresses);
+            };
+            let block_bytes = (block_size * self.word_size) as MemoryAddress;
+            match (piece - piece % block_bytes).checked_add(block_bytes) {
+                Some(next) if next <= last => piece = next,
+                _ => return Ok(addresses),
+            }
+        }
+    }
+
+    /// Moves `address` to the same word in words of [`WORD_SIZE`] bytes.
+    fn rescale(&self, address: MemoryAddress) -> Result<MemoryAddress, Box<dyn Error>> {
+        let word_size = self.word_size as MemoryAddress;
+        let cache_word_size = WORD_SIZE as MemoryAddress;
+
+        (address / word_size)
+            .checked_mul(cache_word_size)
+            .map(|word| word + address % word_size * cache_word_size / word_size)
+            .ok_or_else(|| {
+                format!(
+                    "Address {:#x} does not fit in words of {} bytes",
+                    address, WORD_SIZE
+                )
+                .into()
+            })
+    }
+
+    /// Returns the next line that is not empty.
+    fn next_line(&mut self) -> Option<Result<String, Box<dyn Error>>> {
+        loop {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(err) => return Some(Err(err.into())),
+            }
+
+            if !line.trim().is_empty() {
+                line.truncate(line.trim_end_matches(['\n', '\r']).len());
+                return Some(Ok(line));
+            }
+        }
+    }
+}
+
+/// Checks that `address` fits in `address_width` bits, if there is a
+/// width.
+pub fn check_address(
+    address: MemoryAddress,
+    address_width: Option<u32>,
+) -> Result<(), Box<dyn Error>> {
+    match address_width {
+        Some(width) if width < MemoryAddress::BITS && address >> width != 0 => {
+            Err(format!("Address {:#x} does not fit in {} bits", address, width).into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns the address of the trace, with words of `word_size` bytes, that
+/// the caches see as `address`.
+pub fn trace_address(address: MemoryAddress, word_size: usize) -> MemoryAddress {
+    let word_size = word_size as MemoryAddress;
+    let cache_word_size = WORD_SIZE as MemoryAddress;
+
+    address / cache_word_size * word_size + address % cache_word_size * word_size / cache_word_size
+}
+
+/// Returns the bits the addresses the caches see take, when the addresses of
+/// the trace take `address_width` bits with words of `word_size` bytes.
+pub fn cache_address_width(address_width: u32, word_size: usize) -> u32 {
+    (address_width + WORD_SIZE.ilog2()).saturating_sub(word_size.ilog2())
+}
+
+/// Parses a line of a trace with threads, whose thread has to be one of the
+/// `threads` the header declares.
+fn parse_thread_line(line: &str, threads: usize) -> Result<Line, Box<dyn Error>> {
+    let (thread, access) = line
+        .trim_start()
+        .split_once(char::is_whitespace)
+        .ok_or("Missing operation")?;
+    let thread: usize = thread.parse()?;
+    if thread >= threads {
+        return Err(format!("Thread {} is not one of the {} threads", thread, threads).into());
+    }
+
+    Ok(Line::Access(thread, parse_line(access)?))
+}
+
+fn parse_line(line: &str) -> Result<SizedAccess, Box<dyn Error>> {
+    let mut splited = line.split_whitespace();
+
+    let instruction_number: u8 = splited.next().ok_or("Missing operation")?.parse()?;
+    let address: MemoryAddress =
+        MemoryAddress::from_str_radix(splited.next().ok_or("Missing address")?, 16)?;
+
+    let instruction = match instruction_number {
+        0 => Read(Data),
+        1 => Write,
+        2 => Read(Instruction),
+        _ => return Err(format!("Invalid instruction number: {}", instruction_number).into()),
+    };
+
+    // A field that is not a number starts the text that is ignored
+    let size = splited.next().and_then(|size| size.parse::<usize>().ok());
+    if let Some(size) = size.filter(|size| !ACCESS_SIZES.contains(size)) {
+        return Err(format!("Invalid access size: {}", size).into());
+    }
+
+    Ok((instruction, address, size))
+}
+
+/// Parses a `din` record, whose label is `0` for data reads, `1` for writes,
+/// `2` for instruction reads, `3` for escapes and `4` for flushes.
+fn parse_din_line(line: &str) -> Result<Line, Box<dyn Error>> {
+    let mut splited = line.split_whitespace();
+
+    let label: u8 = splited.next().ok_or("Missing label")?.parse()?;
+    let access_type = match label {
+        0 => Read(Data),
+        1 => Write,
+        2 => Read(Instruction),
+        3 => return Ok(Line::Escape),
+        4 => return Ok(Line::Flush),
+        _ => return Err(format!("Invalid din label: {}", label).into()),
+    };
+    let address: MemoryAddress =
+        MemoryAddress::from_str_radix(splited.next().ok_or("Missing address")?, 16)?;
+
+    Ok(Line::Access(0, (access_type, address, None)))
+}
+
+/// Parses a line of Lackey, skipping the messages of valgrind, which start
+/// with `==`.
+fn parse_lackey_line(line: &str) -> Result<Line, Box<dyn Error>> {
+    if line.starts_with("==") {
+        return Ok(Line::Escape);
+    }
+
+    let (kind, access) = line
+        .trim_start()
+        .split_once(char::is_whitespace)
+        .ok_or("Missing address")?;
+    let (address, size) = access.trim().split_once(',').ok_or("Missing size")?;
+    let address = MemoryAddress::from_str_radix(address, 16)?;
+    let size: usize = size.trim().parse()?;
+    if size == 0 {
+        return Err("Invalid access size: 0".into());
+    }
+
+    let access_type = match kind {
+        "I" => Read(Instruction),
+        "L" => Read(Data),
+        "S" => Write,
+        "M" => return Ok(Line::Modify(address, Some(size))),
+        _ => return Err(format!("Invalid Lackey access: {}", kind).into()),
+    };
+
+    Ok(Line::Access(0, (access_type, address, Some(size))))
+}
+
+impl<R: BufRead> Iterator for TraceReader<R> {
+    type Item = Result<(AccessType, MemoryAddress), Box<dyn Error>>;
+
+    /// Returns the next access, ignoring the thread that makes it.
+    fn next(&mut self) -> Option<Self::Item> {
+        let access = self.next_access()?;
+        Some(access.map(|(_, access_type, address)| (access_type, address)))
+    }
+}
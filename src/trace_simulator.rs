@@ -1,41 +1,89 @@
-use crate::cache::AccessType::{Read, Write};
+use crate::analysis::reuse::ReuseHistograms;
+use crate::cache::AccessType::Read;
 use crate::cache::Cache;
-use crate::cache::ValueType::{Data, Instruction};
+use crate::cache::ValueType::Instruction;
 use crate::cli_parser::ParsedArgs;
 use crate::logger::Logger;
-use crate::MemoryAddress;
+use crate::replacement_policies::opt::NextUses;
+use crate::trace_reader::{self, TraceReader, TraceRecord};
+use crate::{MemoryAddress, WORD_SIZE};
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::error::Error;
 use std::fs::File;
-use std::io;
-use std::io::{BufRead, BufReader};
+use std::io::BufReader;
 use std::rc::Rc;
 
+/// A cache below the first level, with its log.
+type LowerLevel = (Rc<RefCell<Cache>>, Rc<RefCell<Logger>>);
+
 pub struct TraceSimulator {
     cache: Cache,
     instructions_cache: Option<Cache>,
-    file_reader: BufReader<File>,
+    /// Levels below the first one with their logs, from the closest to the
+    /// farthest.
+    lower_levels: Vec<LowerLevel>,
+    trace_reader: TraceReader<BufReader<File>>,
+    next_uses: Option<Rc<NextUses>>,
+    reuse_histograms: Option<ReuseHistograms>,
     logs: Rc<RefCell<Logger>>,
 }
 
 impl TraceSimulator {
-    pub fn new(args: ParsedArgs) -> io::Result<TraceSimulator> {
+    pub fn new(args: ParsedArgs) -> Result<TraceSimulator, Box<dyn Error>> {
         let logs = Rc::new(RefCell::new(Logger::default()));
 
-        let cache = Cache::new(
+        let address_width = args
+            .address_width
+            .map(|width| trace_reader::cache_address_width(width, args.word_size));
+
+        let mut lower_levels: Vec<LowerLevel> = Vec::new();
+        for level in args.lower_levels.iter().rev() {
+            let level_logs = Rc::new(RefCell::new(Logger::default()));
+            let mut level_cache = Cache::new(
+                level.block_size,
+                level.cache_size,
+                &*level.map_strategy_factory,
+                &*level.replacement_policy_factory,
+                level.write_policy,
+                level.write_miss_policy,
+                Rc::clone(&level_logs),
+            );
+            level_cache.set_latency(level.latency);
+            level_cache.set_inclusion(level.inclusion);
+            if let Some(entries) = level.victim_cache {
+                level_cache.set_victim_cache(entries);
+            }
+            if let Some(ref prefetcher_factory) = level.prefetcher_factory {
+                level_cache.set_prefetcher(&**prefetcher_factory);
+            }
+            if let Some((next_level, _)) = lower_levels.last() {
+                level_cache.set_next_level(Rc::clone(next_level));
+            }
+            if let Some(address_width) = address_width {
+                level_logs.borrow_mut().tag_storage = Some(level_cache.tag_storage(address_width));
+            }
+
+            lower_levels.push((Rc::new(RefCell::new(level_cache)), level_logs));
+        }
+        lower_levels.reverse();
+
+        let mut cache = Cache::new(
             args.block_size,
             args.cache_size / (1 + args.split_i_d as usize),
             &*args.map_strategy_factory,
+            &*args.replacement_policy_factory,
             args.write_policy,
             args.write_miss_policy,
             Rc::clone(&logs),
         );
 
-        let instructions_cache = if args.split_i_d {
+        let mut instructions_cache = if args.split_i_d {
             Some(Cache::new(
                 args.block_size,
                 args.cache_size / 2,
                 &*args.map_strategy_factory,
+                &*args.replacement_policy_factory,
                 args.write_policy,
                 args.write_miss_policy,
                 Rc::clone(&logs),
@@ -44,31 +92,65 @@ impl TraceSimulator {
             None
         };
 
-        let file = File::open(args.file_path)?;
-        let file_reader = BufReader::new(file);
+        for first_level in std::iter::once(&mut cache).chain(instructions_cache.as_mut()) {
+            first_level.set_latency(args.latency);
+            if let Some(entries) = args.victim_cache {
+                first_level.set_victim_cache(entries);
+            }
+            if let Some(ref prefetcher_factory) = args.prefetcher_factory {
+                first_level.set_prefetcher(&**prefetcher_factory);
+            }
+            if let Some((next_level, _)) = lower_levels.first() {
+                first_level.set_next_level(Rc::clone(next_level));
+            }
+        }
+
+        if let Some(address_width) = address_width {
+            let tag_storage = std::iter::once(&cache)
+                .chain(instructions_cache.as_ref())
+                .map(|first_level| first_level.tag_storage(address_width))
+                .sum();
+            logs.borrow_mut().tag_storage = Some(tag_storage);
+        }
+
+        let next_uses = args.replacement_policy_factory.lookahead();
+        if let Some(ref next_uses) = next_uses {
+            let block_mask_size = args.block_size.ilog2() + WORD_SIZE.ilog2();
+            let accesses = TraceReader::open_with(&args.file_path, &args)?
+                .map(|access| {
+                    let (access_type, address) = access?;
+                    let cache =
+                        (args.split_i_d && matches!(access_type, Read(Instruction))) as usize;
+                    Ok((cache, address >> block_mask_size))
+                })
+                .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+            next_uses.build(accesses);
+        }
+
+        let reuse_histograms = args
+            .reuse_histograms
+            .then(|| ReuseHistograms::new(args.block_size));
+
+        let trace_reader = TraceReader::open_with(&args.file_path, &args)?;
         Ok(TraceSimulator {
             cache,
-            file_reader,
+            trace_reader,
             instructions_cache,
+            lower_levels,
+            next_uses,
+            reuse_histograms,
             logs,
         })
     }
 
     pub fn run(mut self) -> Result<Logger, Box<dyn Error>> {
-        for line in self.file_reader.lines() {
-            let line = line?;
-
-            let mut splited = line.split_whitespace();
-
-            let instruction_number: u8 = splited.next().unwrap().parse()?;
-            let address: MemoryAddress =
-                MemoryAddress::from_str_radix(splited.next().unwrap(), 16)?;
-
-            let instruction = match instruction_number {
-                0 => Read(Data),
-                1 => Write,
-                2 => Read(Instruction),
-                _ => panic!("Invalid instruction number: {}", instruction_number),
+        while let Some(record) = self.trace_reader.next_record() {
+            let (_, instruction, address) = match record? {
+                TraceRecord::Access(access) => access,
+                TraceRecord::Flush => {
+                    self.flush();
+                    continue;
+                }
             };
 
             if let Some(ref mut cache_i) = self.instructions_cache {
@@ -80,8 +162,74 @@ impl TraceSimulator {
             } else {
                 self.cache.access(instruction, address);
             }
+
+            if let Some(ref next_uses) = self.next_uses {
+                next_uses.advance();
+            }
+            if let Some(ref mut reuse_histograms) = self.reuse_histograms {
+                reuse_histograms.access(instruction, address);
+            }
+        }
+
+        self.cache.report();
+        if let Some(ref cache_i) = self.instructions_cache {
+            cache_i.report();
+        }
+
+        let mut logs = self.logs.take();
+        logs.reuse_histograms = self.reuse_histograms.take();
+        for (level_cache, level_logs) in &self.lower_levels {
+            level_cache.borrow().report();
+            logs.lower_levels.push(level_logs.take());
+        }
+        if !self.lower_levels.is_empty() {
+            logs.effective_capacity = Some(self.effective_capacity());
+        }
+
+        Ok(logs)
+    }
+
+    /// Empties every level, from the first one down so the dirty blocks of
+    /// a level reach memory through the levels below it.
+    fn flush(&mut self) {
+        self.logs.borrow_mut().flush();
+        self.cache.flush();
+        if let Some(ref mut cache_i) = self.instructions_cache {
+            cache_i.flush();
+        }
+        for (level_cache, _) in &self.lower_levels {
+            level_cache.borrow_mut().flush();
+        }
+    }
+
+    /// Returns the distinct words stored across all the levels, and the words
+    /// all the levels can hold.
+    fn effective_capacity(&self) -> (u128, u128) {
+        let lower_levels: Vec<_> = self
+            .lower_levels
+            .iter()
+            .map(|(level_cache, _)| level_cache.borrow())
+            .collect();
+        let caches: Vec<&Cache> = std::iter::once(&self.cache)
+            .chain(self.instructions_cache.as_ref())
+            .chain(lower_levels.iter().map(|level_cache| &**level_cache))
+            .collect();
+
+        // Blocks are split in pieces of the smallest block size, so the same
+        // words in levels with different block sizes are only counted once
+        let piece_size = caches.iter().map(|c| c.block_size()).min().unwrap_or(1);
+        let piece_bytes = (piece_size * WORD_SIZE) as MemoryAddress;
+        let mut pieces = HashSet::new();
+        let mut capacity = 0;
+
+        for cache in caches {
+            capacity += cache.capacity() as u128;
+            let block_bytes = (cache.block_size() * WORD_SIZE) as MemoryAddress;
+            for address in cache.stored_blocks() {
+                pieces.extend((address..address + block_bytes).step_by(piece_bytes as usize));
+            }
         }
 
-        Ok(self.logs.take())
+        (pieces.len() as u128 * piece_size as u128, capacity)
     }
 }
@@ -0,0 +1,74 @@
+use std::collections::VecDeque;
+
+use crate::MemoryAddress;
+
+/// A small fully associative cache that keeps the blocks evicted from a
+/// cache, so a miss on a recently evicted block does not go to the next level.
+///
+/// Blocks are stored by the address of their first word and leave in LRU
+/// order.
+pub struct VictimCache {
+    entries: usize,
+    /// Address of every block and whether it is dirty, from the least to the
+    /// most recently used one.
+    blocks: VecDeque<(MemoryAddress, bool)>,
+}
+
+impl VictimCache {
+    pub fn new(entries: usize) -> Self {
+        Self {
+            entries,
+            blocks: VecDeque::with_capacity(entries),
+        }
+    }
+
+    pub fn entries(&self) -> usize {
+        self.entries
+    }
+
+    pub fn contains(&self, address: MemoryAddress) -> bool {
+        self.position(address).is_some()
+    }
+
+    /// Removes the block at `address`, and returns whether it was dirty.
+    pub fn take(&mut self, address: MemoryAddress) -> Option<bool> {
+        let position = self.position(address)?;
+        self.blocks.remove(position).map(|(_, dirty)| dirty)
+    }
+
+    /// Marks the block at `address` as dirty, and returns whether it is here.
+    pub fn write(&mut self, address: MemoryAddress) -> bool {
+        let Some(position) = self.position(address) else {
+            return false;
+        };
+
+        self.blocks.remove(position);
+        self.blocks.push_back((address, true));
+        true
+    }
+
+    /// Stores an evicted block, and returns the least recently used block if
+    /// there was no room for both.
+    pub fn insert(&mut self, address: MemoryAddress, dirty: bool) -> Option<(MemoryAddress, bool)> {
+        if self.entries == 0 {
+            return Some((address, dirty));
+        }
+
+        let evicted = (self.blocks.len() == self.entries)
+            .then(|| self.blocks.pop_front())
+            .flatten();
+        self.blocks.push_back((address, dirty));
+        evicted
+    }
+
+    /// Returns the address of every stored block.
+    pub fn blocks(&self) -> impl Iterator<Item = MemoryAddress> + '_ {
+        self.blocks.iter().map(|&(address, _)| address)
+    }
+
+    fn position(&self, address: MemoryAddress) -> Option<usize> {
+        self.blocks
+            .iter()
+            .position(|&(block_address, _)| block_address == address)
+    }
+}
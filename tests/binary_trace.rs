@@ -0,0 +1,126 @@
+use cache_simulator::{
+    binary_trace::BinaryEncoder,
+    cache::{
+        AccessType::{Read, Write},
+        ValueType::{Data, Instruction},
+    },
+    trace_reader::{RawRecord, TraceFormat, TraceReader, TraceRecord},
+};
+use std::io::Cursor;
+
+fn binary_reader(bytes: Vec<u8>) -> TraceReader<Cursor<Vec<u8>>> {
+    let mut reader = TraceReader::new(Cursor::new(bytes));
+    reader.set_format(TraceFormat::Binary);
+    reader
+}
+
+#[test]
+fn records_survive_a_round_trip() {
+    let records = [
+        RawRecord::Access(1, (Read(Instruction), 0x40bc74, None)),
+        RawRecord::Access(0, (Write, 0x7fff_fffc, Some(8))),
+        RawRecord::Flush,
+        RawRecord::Access(0, (Read(Data), 0x10, None)),
+    ];
+    let mut encoder = BinaryEncoder::new(Cursor::new(Vec::new()), Some(2)).unwrap();
+    for record in &records {
+        encoder.write(record).unwrap();
+    }
+    let bytes = encoder.finish().unwrap().into_inner();
+
+    // A header of 17 bytes, and records of a byte for the operation, the
+    // changes of thread, the varint address differences and the sizes
+    assert_eq!(
+        bytes.len(),
+        17 + (1 + 1 + 4) + (1 + 1 + 5 + 1) + 1 + (1 + 5)
+    );
+    let mut reader = binary_reader(bytes);
+    assert_eq!(reader.threads().unwrap(), Some(2));
+
+    let record = reader.next_raw_record().unwrap().unwrap();
+    assert!(matches!(
+        record,
+        RawRecord::Access(1, (Read(Instruction), 0x40bc74, None))
+    ));
+    let record = reader.next_record().unwrap().unwrap();
+    assert!(matches!(
+        record,
+        TraceRecord::Access((0, Write, 0x7fff_fffc))
+    ));
+    assert!(matches!(
+        reader.next_record().unwrap().unwrap(),
+        TraceRecord::Flush
+    ));
+    let (access_type, address) = reader.next().unwrap().unwrap();
+    assert!(matches!(access_type, Read(Data)));
+    assert_eq!(address, 0x10);
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn traces_shorter_than_their_header_says_are_rejected() {
+    let mut encoder = BinaryEncoder::new(Cursor::new(Vec::new()), None).unwrap();
+    encoder
+        .write(&RawRecord::Access(0, (Read(Data), 0x1234, None)))
+        .unwrap();
+    let mut bytes = encoder.finish().unwrap().into_inner();
+    bytes.pop();
+
+    let mut reader = binary_reader(bytes);
+    assert_eq!(reader.threads().unwrap(), None);
+    assert!(reader.next().unwrap().is_err());
+
+    let mut reader = binary_reader(b"0 1234\n".to_vec());
+    assert!(reader.threads().is_err());
+}
+
+/// Builds a binary trace of `threads` threads with a single `record`.
+fn single_record(threads: u32, record: &[u8]) -> Vec<u8> {
+    let mut bytes = b"CTRC\x01".to_vec();
+    bytes.extend(threads.to_le_bytes());
+    bytes.extend(1u64.to_le_bytes());
+    bytes.extend(record);
+    bytes
+}
+
+#[test]
+fn records_with_invalid_sizes_or_threads_are_rejected() {
+    // A data read of 0 bytes
+    let mut reader = binary_reader(single_record(0, &[0b100, 0, 0]));
+    assert!(reader.next().unwrap().is_err());
+
+    // A data read of thread 5 in a trace of 2 threads
+    let mut reader = binary_reader(single_record(2, &[0b1000, 5, 0]));
+    assert!(reader.next_access().unwrap().is_err());
+    let mut reader = binary_reader(single_record(2, &[0b1000, 1, 0]));
+    assert!(matches!(
+        reader.next_access().unwrap(),
+        Ok((1, Read(Data), 0))
+    ));
+
+    // The encoder refuses the sizes the decoder would reject
+    let mut encoder = BinaryEncoder::new(Cursor::new(Vec::new()), None).unwrap();
+    assert!(encoder
+        .write(&RawRecord::Access(0, (Read(Instruction), 0x10, Some(0))))
+        .is_err());
+}
+
+#[test]
+fn sizes_text_traces_can_not_hold_are_kept() {
+    // Lackey writes accesses of any size, such as 3 or 11 bytes
+    let mut encoder = BinaryEncoder::new(Cursor::new(Vec::new()), None).unwrap();
+    for size in [3, 11] {
+        encoder
+            .write(&RawRecord::Access(0, (Read(Data), 0x10, Some(size))))
+            .unwrap();
+    }
+    let mut reader = binary_reader(encoder.finish().unwrap().into_inner());
+
+    for size in [3, 11] {
+        assert!(matches!(
+            reader.next_raw_record().unwrap().unwrap(),
+            RawRecord::Access(0, (Read(Data), 0x10, Some(s))) if s == size
+        ));
+    }
+    assert!(reader.next_raw_record().is_none());
+}
@@ -0,0 +1,198 @@
+use cache_simulator::{
+    cache::{
+        AccessType::{Read, Write},
+        Cache,
+        ValueType::Data,
+        WriteMissPolicy, WritePolicy,
+    },
+    coherence::{
+        directory::SharerFormat, mesi::Mesi, moesi::Moesi, msi::Msi, Bus, CoherenceProtocol,
+    },
+    logger::Logger,
+    map_strategies::fully_associative::FullyAssociativeFactory,
+    replacement_policies::lru::LruFactory,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+type Core = (Rc<RefCell<Cache>>, Rc<RefCell<Logger>>);
+
+/// Builds `count` write-back caches connected to a bus that uses `protocol`.
+fn cores(count: usize, protocol: Rc<dyn CoherenceProtocol>) -> (Rc<RefCell<Bus>>, Vec<Core>) {
+    connect(count, Bus::new(protocol))
+}
+
+/// Builds `count` write-back caches connected to `bus`.
+fn connect(count: usize, bus: Bus) -> (Rc<RefCell<Bus>>, Vec<Core>) {
+    let bus = Rc::new(RefCell::new(bus));
+    let cores = (0..count)
+        .map(|_| {
+            let log = Rc::new(RefCell::new(Logger::default()));
+            let cache = Rc::new(RefCell::new(Cache::new(
+                2,
+                4,
+                &FullyAssociativeFactory,
+                &LruFactory,
+                WritePolicy::WriteBack,
+                WriteMissPolicy::WriteAllocate,
+                Rc::clone(&log),
+            )));
+            Bus::connect(&bus, &cache);
+            (cache, log)
+        })
+        .collect();
+    (bus, cores)
+}
+
+#[test]
+fn modified_blocks_are_supplied_by_their_owner() {
+    let (_bus, cores) = cores(2, Rc::new(Mesi));
+    let (ref core_0, ref log_0) = cores[0];
+    let (ref core_1, ref log_1) = cores[1];
+
+    core_0.borrow_mut().access(Write, 0x00);
+    core_1.borrow_mut().access(Read(Data), 0x00);
+    // Both copies are shared now, so writing needs an upgrade
+    core_0.borrow_mut().access(Write, 0x04);
+
+    let log_0 = log_0.borrow();
+    let coherence_0 = log_0.coherence.unwrap();
+    assert_eq!(coherence_0.bus_read_exclusives, 1);
+    assert_eq!(coherence_0.bus_upgrades, 1);
+    assert_eq!(coherence_0.interventions, 1);
+    // The block is flushed when it is supplied
+    assert_eq!(log_0.memory_writes, 2);
+
+    let log_1 = log_1.borrow();
+    let coherence_1 = log_1.coherence.unwrap();
+    assert_eq!(coherence_1.bus_reads, 1);
+    assert_eq!(coherence_1.invalidations, 1);
+    assert_eq!(log_1.memory_reads, 0);
+}
+
+#[test]
+fn invalidated_blocks_cause_coherence_misses() {
+    let (_bus, cores) = cores(2, Rc::new(Mesi));
+    let (ref core_0, ref log_0) = cores[0];
+    let (ref core_1, ref log_1) = cores[1];
+
+    // An exclusive block is written without telling the bus
+    core_0.borrow_mut().access(Read(Data), 0x00);
+    core_0.borrow_mut().access(Write, 0x00);
+    assert_eq!(log_0.borrow().coherence.unwrap().bus_transactions(), 1);
+
+    core_1.borrow_mut().access(Write, 0x00);
+    core_0.borrow_mut().access(Read(Data), 0x00);
+
+    let log_0 = log_0.borrow();
+    let coherence_0 = log_0.coherence.unwrap();
+    assert_eq!(coherence_0.invalidations, 1);
+    assert_eq!(coherence_0.coherence_misses, 1);
+    assert_eq!(log_0.data_misses, 2);
+
+    let coherence_1 = log_1.borrow().coherence.unwrap();
+    assert_eq!(coherence_1.bus_read_exclusives, 1);
+    assert_eq!(coherence_1.interventions, 1);
+}
+
+/// Writes a block in one core and reads it from the other one twice, with an
+/// eviction in between, returning the logs of both cores.
+fn share_written_block(protocol: Rc<dyn CoherenceProtocol>) -> (Logger, Logger) {
+    let (_bus, cores) = cores(2, protocol);
+    let (ref core_0, ref log_0) = cores[0];
+    let (ref core_1, ref log_1) = cores[1];
+
+    core_0.borrow_mut().access(Read(Data), 0x00);
+    core_0.borrow_mut().access(Write, 0x00);
+    core_1.borrow_mut().access(Read(Data), 0x00);
+    // Fill the reader so the block leaves it
+    for address in [0x08, 0x10, 0x18, 0x20] {
+        core_1.borrow_mut().access(Read(Data), address);
+    }
+    core_1.borrow_mut().access(Read(Data), 0x00);
+
+    (log_0.take(), log_1.take())
+}
+
+#[test]
+fn protocols_differ_in_bus_traffic_and_memory_writes() {
+    let (msi_0, msi_1) = share_written_block(Rc::new(Msi));
+    let (mesi_0, mesi_1) = share_written_block(Rc::new(Mesi));
+    let (moesi_0, moesi_1) = share_written_block(Rc::new(Moesi));
+
+    // MSI needs an upgrade to write a block no one else has
+    assert_eq!(msi_0.coherence.unwrap().bus_upgrades, 1);
+    assert_eq!(mesi_0.coherence.unwrap().bus_upgrades, 0);
+    assert_eq!(moesi_0.coherence.unwrap().bus_upgrades, 0);
+
+    // Once written back, the second read of the block goes to memory
+    assert_eq!(msi_1.memory_reads, 10);
+    assert_eq!(mesi_0.memory_writes, 2);
+    assert_eq!(mesi_0.coherence.unwrap().interventions, 1);
+    assert_eq!(mesi_1.memory_reads, 10);
+
+    // The owner keeps supplying the block instead of writing it back
+    assert_eq!(moesi_0.memory_writes, 0);
+    assert_eq!(moesi_0.coherence.unwrap().interventions, 2);
+    assert_eq!(moesi_1.memory_reads, 8);
+}
+
+#[test]
+fn limited_directories_evict_sharers_that_do_not_fit() {
+    // Every read of a single pointer directory evicts the other core
+    for (format, evictions) in [
+        (SharerFormat::FullBitVector, 0),
+        (SharerFormat::NoBroadcast { pointers: 1 }, 2),
+    ] {
+        let (bus, cores) = connect(2, Bus::with_directory(Rc::new(Mesi), format, 2));
+        let (ref core_0, ref log_0) = cores[0];
+        let (ref core_1, _) = cores[1];
+
+        core_0.borrow_mut().access(Read(Data), 0x00);
+        core_1.borrow_mut().access(Read(Data), 0x00);
+        core_0.borrow_mut().access(Read(Data), 0x00);
+
+        let stats = bus.borrow().directory_stats().unwrap();
+        assert_eq!(stats.evictions, evictions);
+        let coherence_0 = log_0.borrow().coherence.unwrap();
+        assert_eq!(coherence_0.invalidations, evictions / 2);
+        assert_eq!(coherence_0.coherence_misses, evictions / 2);
+
+        if evictions == 0 {
+            // A request and a reply for the first read, which finds no
+            // sharers, and a forward to the owner and its answer for the
+            // second one
+            assert_eq!(stats.lookups, 2);
+            assert_eq!(stats.messages, 6);
+        }
+    }
+}
+
+#[test]
+fn coherence_misses_tell_true_from_false_sharing() {
+    let (bus, cores) = cores(2, Rc::new(Mesi));
+    let (ref core_0, ref log_0) = cores[0];
+    let (ref core_1, _) = cores[1];
+
+    // Different words of the same block
+    core_0.borrow_mut().access(Write, 0x00);
+    core_1.borrow_mut().access(Write, 0x04);
+    core_0.borrow_mut().access(Read(Data), 0x00);
+    // The same word
+    core_1.borrow_mut().access(Write, 0x00);
+    core_0.borrow_mut().access(Read(Data), 0x00);
+
+    let coherence_0 = log_0.borrow().coherence.unwrap();
+    assert_eq!(coherence_0.coherence_misses, 2);
+    assert_eq!(coherence_0.false_sharing_misses, 1);
+    assert_eq!(coherence_0.true_sharing_misses, 1);
+
+    let falsely_shared = bus.borrow().most_falsely_shared(10);
+    assert_eq!(falsely_shared.len(), 1);
+    assert_eq!(falsely_shared[0].address, 0x00);
+    assert_eq!(falsely_shared[0].misses, 1);
+    assert_eq!(
+        falsely_shared[0].cores.iter().copied().collect::<Vec<_>>(),
+        [0, 1]
+    );
+}
@@ -1,5 +1,6 @@
 use cache_simulator::{
     map_strategies::{direct_map::DirectMapFactory, MapStrategyFactory},
+    replacement_policies::lru::LruFactory,
     MemoryAddress,
 };
 const FACTORY: DirectMapFactory = DirectMapFactory;
@@ -10,7 +11,7 @@ fn test_mapping(
     address: MemoryAddress,
     correct_map: MemoryAddress,
 ) {
-    let mut dm = FACTORY.generate(block_size, cache_size);
+    let mut dm = FACTORY.generate(block_size, cache_size, &LruFactory);
     assert_eq!(dm.map(address, &[]), correct_map);
 }
 
@@ -20,7 +21,7 @@ fn test_tag(
     address: MemoryAddress,
     correct_tag: MemoryAddress,
 ) {
-    let dm = FACTORY.generate(block_size, cache_size);
+    let dm = FACTORY.generate(block_size, cache_size, &LruFactory);
     assert_eq!(dm.get_tag(address), correct_tag);
 }
 
@@ -30,6 +31,7 @@ fn mapping() {
     test_mapping(1, 16, 0b1101_0010_1010, 0b1010);
     test_mapping(1, 1, 0b10_1010_1011_1010, 0b0);
     test_mapping(16, 1, 0b10_1010_1011_1010, 0b0);
+    test_mapping(4, 16, 0x7ffd_1234_5678, 0b0111);
 }
 
 #[test]
@@ -38,4 +40,11 @@ fn tags() {
     test_tag(1, 16, 0b1101_0010_1010, 0b110100);
     test_tag(1, 1, 0b10_1010_1011_1010, 0b101010101110);
     test_tag(16, 1, 0b10_1010_1011_1010, 0b10101010);
+    test_tag(4, 16, 0x7ffd_1234_5678, 0x007f_fd12_3456);
+}
+
+#[test]
+fn index_bits() {
+    assert_eq!(FACTORY.generate(4, 16, &LruFactory).index_bits(), 4);
+    assert_eq!(FACTORY.generate(4, 1, &LruFactory).index_bits(), 0);
 }
@@ -0,0 +1,84 @@
+use cache_simulator::{
+    cache_block::CacheBlock,
+    map_strategies::{fully_associative::FullyAssociativeFactory, MapStrategy, MapStrategyFactory},
+    replacement_policies::lru::LruFactory,
+    MemoryAddress,
+};
+const FACTORY: FullyAssociativeFactory = FullyAssociativeFactory;
+
+/// Maps `address` and places its block, like the cache does on a miss.
+fn access(
+    fa: &mut dyn MapStrategy,
+    blocks: &mut [CacheBlock],
+    address: MemoryAddress,
+) -> MemoryAddress {
+    let idx = fa.map(address, blocks);
+    let tag = fa.get_tag(address);
+    let block = &blocks[idx as usize];
+    if !block.valid || !block.is_match(tag) {
+        fa.place(idx as usize, tag, blocks);
+        let block = &mut blocks[idx as usize];
+        block.valid = true;
+        block.tag = tag;
+    }
+    idx
+}
+
+#[test]
+fn tags() {
+    let fa = FACTORY.generate(4, 16, &LruFactory);
+    assert_eq!(fa.get_tag(0b1101_0010_1010), 0b1101_0010);
+    let fa = FACTORY.generate(1, 16, &LruFactory);
+    assert_eq!(fa.get_tag(0b1101_0010_1010), 0b1101001010);
+}
+
+#[test]
+fn mapping() {
+    let mut fa = FACTORY.generate(1, 4, &LruFactory);
+    let mut blocks = vec![CacheBlock::default(); 4];
+
+    // Empty blocks are used in order
+    for (i, address) in [0x10, 0x20, 0x30, 0x40].into_iter().enumerate() {
+        assert_eq!(access(&mut *fa, &mut blocks, address), i as MemoryAddress);
+    }
+
+    // Hits find the block, misses replace the least recently used one
+    assert_eq!(access(&mut *fa, &mut blocks, 0x10), 0);
+    assert_eq!(access(&mut *fa, &mut blocks, 0x50), 1);
+    assert_eq!(access(&mut *fa, &mut blocks, 0x20), 2);
+    assert_eq!(access(&mut *fa, &mut blocks, 0x50), 1);
+}
+
+#[test]
+fn mapping_without_placing() {
+    let mut fa = FACTORY.generate(1, 2, &LruFactory);
+    let mut blocks = vec![CacheBlock::default(); 2];
+
+    access(&mut *fa, &mut blocks, 0x10);
+    access(&mut *fa, &mut blocks, 0x20);
+
+    // The cache does not place the block, so 0x10 must still be found
+    assert_eq!(fa.map(0x30, &blocks), 0);
+    assert_eq!(access(&mut *fa, &mut blocks, 0x10), 0);
+    assert_eq!(access(&mut *fa, &mut blocks, 0x30), 1);
+}
+
+#[test]
+fn placing_and_invalidating() {
+    let mut fa = FACTORY.generate(1, 2, &LruFactory);
+    let mut blocks = vec![CacheBlock::default(); 2];
+
+    access(&mut *fa, &mut blocks, 0x10);
+    access(&mut *fa, &mut blocks, 0x20);
+
+    // Placing a block forgets the one it replaces
+    assert_eq!(access(&mut *fa, &mut blocks, 0x30), 0);
+    assert_eq!(fa.find(0x10, &blocks), None);
+    assert_eq!(fa.find(0x30, &blocks), Some(0));
+
+    // Invalidated ways are filled before asking for a victim
+    fa.invalidate(1, &blocks);
+    blocks[1].valid = false;
+    assert_eq!(fa.find(0x20, &blocks), None);
+    assert_eq!(access(&mut *fa, &mut blocks, 0x40), 1);
+}
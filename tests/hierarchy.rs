@@ -0,0 +1,191 @@
+use cache_simulator::{
+    cache::{
+        AccessType::{Read, Write},
+        Cache, InclusionPolicy,
+        ValueType::Data,
+        WriteMissPolicy, WritePolicy,
+    },
+    logger::Logger,
+    map_strategies::{direct_map::DirectMapFactory, fully_associative::FullyAssociativeFactory},
+    replacement_policies::lru::LruFactory,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Builds a write-back cache with its own log.
+fn level(block_size: usize, cache_size: usize) -> (Cache, Rc<RefCell<Logger>>) {
+    let log = Rc::new(RefCell::new(Logger::default()));
+    let cache = Cache::new(
+        block_size,
+        cache_size,
+        &FullyAssociativeFactory,
+        &LruFactory,
+        WritePolicy::WriteBack,
+        WriteMissPolicy::WriteAllocate,
+        Rc::clone(&log),
+    );
+    (cache, log)
+}
+
+#[test]
+fn misses_go_to_the_next_level() {
+    let (mut l1, l1_log) = level(2, 1);
+    let (l2, l2_log) = level(4, 4);
+    l1.set_next_level(Rc::new(RefCell::new(l2)));
+
+    // Both words of 0x00 are in the same L2 block
+    l1.access(Read(Data), 0x00);
+    l1.access(Read(Data), 0x08);
+    l1.access(Read(Data), 0x00);
+
+    let l1_log = l1_log.borrow();
+    assert_eq!(l1_log.data_misses, 3);
+    assert_eq!(l1_log.memory_reads, 6);
+
+    let l2_log = l2_log.borrow();
+    assert_eq!(l2_log.data_references, 3);
+    assert_eq!(l2_log.data_misses, 1);
+    assert_eq!(l2_log.memory_reads, 4);
+}
+
+#[test]
+fn large_blocks_touch_several_blocks_below() {
+    let (mut l1, _) = level(4, 1);
+    let (l2, l2_log) = level(1, 8);
+    l1.set_next_level(Rc::new(RefCell::new(l2)));
+
+    l1.access(Read(Data), 0x04);
+
+    let l2_log = l2_log.borrow();
+    assert_eq!(l2_log.data_references, 4);
+    assert_eq!(l2_log.data_misses, 4);
+}
+
+#[test]
+fn write_backs_do_not_read_whole_blocks() {
+    let (mut l1, _) = level(2, 1);
+    let log = Rc::new(RefCell::new(Logger::default()));
+    let l2 = Cache::new(
+        2,
+        4,
+        &DirectMapFactory,
+        &LruFactory,
+        WritePolicy::WriteBack,
+        WriteMissPolicy::WriteAllocate,
+        Rc::clone(&log),
+    );
+    let l2 = Rc::new(RefCell::new(l2));
+    l1.set_next_level(Rc::clone(&l2));
+
+    l1.access(Write, 0x00);
+    // Make L2 evict its copy, then evict the dirty block from L1
+    l2.borrow_mut().access(Read(Data), 0x20);
+    l1.access(Read(Data), 0x08);
+
+    // The write-back misses in L2, but it has the whole block already
+    let log = log.borrow();
+    assert_eq!(log.data_references, 4);
+    assert_eq!(log.memory_reads, 6);
+}
+
+#[test]
+fn inclusive_levels_invalidate_the_levels_above() {
+    let (mut l1, l1_log) = level(1, 2);
+    let (mut l2, l2_log) = level(1, 1);
+    l2.set_inclusion(InclusionPolicy::Inclusive);
+    l1.set_next_level(Rc::new(RefCell::new(l2)));
+
+    l1.access(Write, 0x00);
+    // L2 evicts 0x00, so L1 drops its dirty copy and writes it to memory
+    l1.access(Read(Data), 0x04);
+    l1.access(Read(Data), 0x00);
+
+    let l1_log = l1_log.borrow();
+    assert_eq!(l1_log.back_invalidations, 2);
+    assert_eq!(l1_log.data_misses, 3);
+    assert_eq!(l1_log.memory_writes, 1);
+
+    let l2_log = l2_log.borrow();
+    assert_eq!(l2_log.memory_writes, 0);
+}
+
+#[test]
+fn exclusive_levels_hold_the_victims_of_the_level_above() {
+    let (mut l1, l1_log) = level(1, 1);
+    let (mut l2, l2_log) = level(1, 4);
+    l2.set_inclusion(InclusionPolicy::Exclusive);
+    l1.set_next_level(Rc::new(RefCell::new(l2)));
+
+    l1.access(Read(Data), 0x00);
+    l1.access(Read(Data), 0x04);
+    // 0x00 was moved to L2 when L1 evicted it
+    l1.access(Read(Data), 0x00);
+
+    let l1_log = l1_log.borrow();
+    assert_eq!(l1_log.data_misses, 3);
+    assert_eq!(l1_log.memory_writes, 2);
+
+    let l2_log = l2_log.borrow();
+    assert_eq!(l2_log.data_references, 3);
+    assert_eq!(l2_log.data_misses, 2);
+    assert_eq!(l2_log.memory_reads, 2);
+}
+
+#[test]
+fn tags_store_the_bits_the_index_and_offset_do_not() {
+    let (l1, _) = level(4, 8);
+    let direct_mapped = Cache::new(
+        4,
+        8,
+        &DirectMapFactory,
+        &LruFactory,
+        WritePolicy::WriteBack,
+        WriteMissPolicy::WriteAllocate,
+        Rc::new(RefCell::new(Logger::default())),
+    );
+
+    // 16 byte blocks leave 44 of 48 bits, and 8 blocks need 3 of them
+    assert_eq!(l1.tag_storage(48), 8 * 44);
+    assert_eq!(direct_mapped.tag_storage(48), 8 * 41);
+    assert_eq!(direct_mapped.tag_storage(32), 8 * 25);
+}
+
+#[test]
+fn flushes_write_back_the_dirty_blocks() {
+    let (mut l1, l1_log) = level(1, 2);
+    let (l2, l2_log) = level(1, 4);
+    let l2 = Rc::new(RefCell::new(l2));
+    l1.set_next_level(Rc::clone(&l2));
+
+    l1.access(Write, 0x00);
+    l1.access(Read(Data), 0x04);
+    l1.flush();
+    assert_eq!(l1_log.borrow().memory_writes, 1);
+    assert_eq!(l1.stored_blocks().count(), 0);
+
+    // The written block reaches memory once the level below is flushed too
+    l2.borrow_mut().flush();
+    assert_eq!(l2_log.borrow().memory_writes, 1);
+    l1.access(Read(Data), 0x00);
+    assert_eq!(l1_log.borrow().data_misses, 3);
+}
+
+#[test]
+fn adding_logs_keeps_the_levels_without_pair() {
+    let mut log = Logger::default();
+    log.lower_levels.push(Logger {
+        memory_reads: 4,
+        ..Default::default()
+    });
+    let mut other_log = Logger::default();
+    for memory_reads in [1, 2] {
+        other_log.lower_levels.push(Logger {
+            memory_reads,
+            ..Default::default()
+        });
+    }
+
+    let added = log + other_log;
+    let memory_reads: Vec<_> = added.lower_levels.iter().map(|l| l.memory_reads).collect();
+    assert_eq!(memory_reads, [5, 2]);
+}
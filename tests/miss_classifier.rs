@@ -0,0 +1,18 @@
+use cache_simulator::miss_classifier::{MissClassifier, MissKind};
+
+#[test]
+fn three_c() {
+    // Two blocks of one word
+    let mut classifier = MissClassifier::new(1, 2);
+
+    assert_eq!(classifier.miss(0x00), MissKind::Compulsory);
+    assert_eq!(classifier.miss(0x08), MissKind::Compulsory);
+    // Both blocks fit, so the cache lost 0x00 because of where it mapped it
+    assert_eq!(classifier.miss(0x00), MissKind::Conflict);
+    classifier.hit(0x08);
+
+    assert_eq!(classifier.miss(0x10), MissKind::Compulsory);
+    // The least recently used block was 0x00
+    assert_eq!(classifier.miss(0x00), MissKind::Capacity);
+    assert_eq!(classifier.miss(0x10), MissKind::Conflict);
+}
@@ -0,0 +1,135 @@
+use cache_simulator::{
+    cache::{AccessType::Read, Cache, ValueType::Data, WriteMissPolicy, WritePolicy},
+    logger::Logger,
+    map_strategies::fully_associative::FullyAssociativeFactory,
+    prefetchers::{
+        next_line::NextLineFactory, stream::StreamFactory, stride::StrideFactory, Outcome,
+        PrefetcherFactory,
+    },
+    replacement_policies::lru::LruFactory,
+    HIT_DURATION, MISS_DURATION,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[test]
+fn next_line() {
+    let mut prefetcher = NextLineFactory { degree: 2 }.generate(1);
+
+    assert_eq!(prefetcher.access(0x14, Outcome::Miss), vec![0x18, 0x1c]);
+    assert_eq!(
+        prefetcher.access(0x18, Outcome::PrefetchHit),
+        vec![0x1c, 0x20]
+    );
+    assert!(prefetcher.access(0x14, Outcome::Hit).is_empty());
+}
+
+#[test]
+fn stride() {
+    let mut prefetcher = StrideFactory { degree: 1 }.generate(1);
+
+    // The stride has to repeat twice before it is trusted
+    assert!(prefetcher.access(0x100, Outcome::Miss).is_empty());
+    assert!(prefetcher.access(0x0f0, Outcome::Miss).is_empty());
+    assert!(prefetcher.access(0x0e0, Outcome::Miss).is_empty());
+    assert_eq!(prefetcher.access(0x0d0, Outcome::Miss), vec![0x0c0]);
+    assert_eq!(prefetcher.access(0x0c0, Outcome::Miss), vec![0x0b0]);
+
+    // Other regions are followed on their own
+    assert!(prefetcher.access(0x2000, Outcome::Miss).is_empty());
+    assert_eq!(prefetcher.access(0x0b0, Outcome::Hit), vec![0x0a0]);
+}
+
+#[test]
+fn stream() {
+    let mut prefetcher = StreamFactory {
+        streams: 1,
+        depth: 2,
+    }
+    .generate(1);
+
+    assert_eq!(prefetcher.access(0x00, Outcome::Miss), vec![0x04, 0x08]);
+    assert_eq!(
+        prefetcher.access(0x04, Outcome::PrefetchHit),
+        vec![0x08, 0x0c]
+    );
+    // A miss outside the stream replaces it
+    assert_eq!(prefetcher.access(0x40, Outcome::Miss), vec![0x44, 0x48]);
+    assert!(prefetcher.access(0x0c, Outcome::PrefetchHit).is_empty());
+}
+
+#[test]
+fn prefetched_blocks_are_counted() {
+    let log = Rc::new(RefCell::new(Logger::default()));
+    let mut cache = Cache::new(
+        1,
+        2,
+        &FullyAssociativeFactory,
+        &LruFactory,
+        WritePolicy::WriteBack,
+        WriteMissPolicy::WriteAllocate,
+        Rc::clone(&log),
+    );
+    cache.set_prefetcher(&NextLineFactory { degree: 1 });
+
+    cache.access(Read(Data), 0x00);
+    // Still on the way, as the access right after the miss does not wait
+    // for it
+    cache.access(Read(Data), 0x04);
+    // Evicts the block prefetched by the last access, which was not used
+    cache.access(Read(Data), 0x20);
+    cache.access(Read(Data), 0x40);
+
+    let log = log.borrow();
+    let prefetches = log.prefetches.unwrap();
+    assert_eq!(log.data_misses, 3);
+    assert_eq!(prefetches.issued, 4);
+    assert_eq!(prefetches.useful, 1);
+    assert_eq!(prefetches.late, 1);
+    assert_eq!(prefetches.pollution, 2);
+}
+
+/// Builds a cache of two one-word blocks that prefetches the next block.
+fn next_line_cache() -> (Cache, Rc<RefCell<Logger>>) {
+    let log = Rc::new(RefCell::new(Logger::default()));
+    let mut cache = Cache::new(
+        1,
+        2,
+        &FullyAssociativeFactory,
+        &LruFactory,
+        WritePolicy::WriteBack,
+        WriteMissPolicy::WriteAllocate,
+        Rc::clone(&log),
+    );
+    cache.set_prefetcher(&NextLineFactory { degree: 1 });
+    (cache, log)
+}
+
+#[test]
+fn prefetches_only_cost_the_time_waited_for_them() {
+    let (mut cache, log) = next_line_cache();
+
+    // The prefetch of 0x04 is fetched in the background
+    cache.access(Read(Data), 0x00);
+    assert_eq!(log.borrow().running_time, HIT_DURATION + MISS_DURATION);
+
+    // Using it right away waits for the whole fetch, like a miss would
+    cache.access(Read(Data), 0x04);
+    assert_eq!(
+        log.borrow().running_time,
+        2 * (HIT_DURATION + MISS_DURATION)
+    );
+    assert_eq!(log.borrow().prefetches.unwrap().late, 1);
+}
+
+#[test]
+fn invalidated_prefetches_are_pollution() {
+    let (mut cache, log) = next_line_cache();
+
+    cache.access(Read(Data), 0x00);
+    cache.flush();
+
+    let prefetches = log.borrow().prefetches.unwrap();
+    assert_eq!(prefetches.issued, 1);
+    assert_eq!(prefetches.pollution, 1);
+}
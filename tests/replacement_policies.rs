@@ -0,0 +1,436 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use cache_simulator::cache::{
+    AccessType::{Read, Write},
+    Cache,
+    ValueType::Data,
+    WriteMissPolicy, WritePolicy,
+};
+use cache_simulator::cli_parser::LevelConfig;
+use cache_simulator::logger::Logger;
+use cache_simulator::map_strategies::{
+    fully_associative::FullyAssociativeFactory, set_associative::SetAssociativeFactory,
+    MapStrategyFactory,
+};
+use cache_simulator::replacement_policies::{
+    arc::ArcFactory,
+    clock::ClockFactory,
+    fifo::FifoFactory,
+    lfu::{LfruFactory, LfuFactory},
+    lru::{BipFactory, LipFactory, LruFactory},
+    opt::{NextUses, OptFactory},
+    plru::{BitPlruFactory, TreePlruFactory},
+    random::RandomFactory,
+    rrip::{BrripFactory, SrripFactory},
+    set_dueling::{SetDueling, SetRole},
+    ReplacementPolicy, ReplacementPolicyFactory,
+};
+
+fn fill_all(policy: &mut dyn ReplacementPolicy, sets: usize, ways: usize) {
+    for set in 0..sets {
+        for way in 0..ways {
+            policy.fill(set, way, (set * ways + way) as u64);
+        }
+    }
+}
+
+#[test]
+fn lru() {
+    let mut policy = LruFactory.generate(2, 4);
+    fill_all(&mut *policy, 2, 4);
+
+    assert_eq!(policy.victim(0), 0);
+    policy.touch(0, 0);
+    assert_eq!(policy.victim(0), 1);
+    policy.touch(0, 1);
+    policy.touch(0, 2);
+    assert_eq!(policy.victim(0), 3);
+
+    // Sets are independent of each other
+    assert_eq!(policy.victim(1), 0);
+
+    policy.invalidate(1, 2);
+    assert_eq!(policy.victim(1), 2);
+}
+
+#[test]
+fn fifo() {
+    let mut policy = FifoFactory.generate(1, 4);
+    fill_all(&mut *policy, 1, 4);
+
+    policy.touch(0, 0);
+    assert_eq!(policy.victim(0), 0);
+    policy.fill(0, 0, 0);
+    assert_eq!(policy.victim(0), 1);
+
+    policy.invalidate(0, 3);
+    assert_eq!(policy.victim(0), 3);
+}
+
+#[test]
+fn random_is_reproducible() {
+    let victims = |seed| {
+        let mut policy = RandomFactory { seed }.generate(1, 8);
+        fill_all(&mut *policy, 1, 8);
+        (0..32).map(|_| policy.victim(0)).collect::<Vec<_>>()
+    };
+
+    assert_eq!(victims(7), victims(7));
+    assert_ne!(victims(7), victims(8));
+    assert!(victims(7).iter().all(|&way| way < 8));
+}
+
+#[test]
+fn clock() {
+    let mut policy = ClockFactory.generate(1, 4);
+    fill_all(&mut *policy, 1, 4);
+
+    // Every block is referenced, so the hand clears all bits and comes back
+    assert_eq!(policy.victim(0), 0);
+    policy.fill(0, 0, 0);
+
+    policy.touch(0, 1);
+    assert_eq!(policy.victim(0), 2);
+    policy.fill(0, 2, 2);
+    assert_eq!(policy.victim(0), 3);
+}
+
+#[test]
+fn tree_plru() {
+    let mut policy = TreePlruFactory.generate(2, 4);
+    fill_all(&mut *policy, 2, 4);
+
+    // After filling 0, 1, 2, 3 the tree points to the left half, then to 0
+    assert_eq!(policy.victim(0), 0);
+    policy.touch(0, 0);
+    assert_eq!(policy.victim(0), 2);
+    policy.touch(0, 2);
+    assert_eq!(policy.victim(0), 1);
+
+    policy.invalidate(1, 3);
+    assert_eq!(policy.victim(1), 3);
+}
+
+#[test]
+#[should_panic]
+fn tree_plru_needs_power_of_two_ways() {
+    TreePlruFactory.generate(1, 6);
+}
+
+#[test]
+fn tree_plru_rejects_other_ways_before_generating() {
+    assert!(TreePlruFactory.check_ways(6).is_err());
+    assert!(TreePlruFactory.check_ways(8).is_ok());
+    assert!(LruFactory.check_ways(6).is_ok());
+}
+
+#[test]
+fn bit_plru_with_one_way() {
+    let mut policy = BitPlruFactory.generate(2, 1);
+    fill_all(&mut *policy, 2, 1);
+
+    assert_eq!(policy.victim(0), 0);
+    policy.touch(1, 0);
+    assert_eq!(policy.victim(1), 0);
+}
+
+#[test]
+fn bit_plru() {
+    let mut policy = BitPlruFactory.generate(1, 70);
+    fill_all(&mut *policy, 1, 70);
+
+    // Filling the last way cleared every other bit
+    assert_eq!(policy.victim(0), 0);
+    for way in 0..65 {
+        policy.touch(0, way);
+    }
+    assert_eq!(policy.victim(0), 65);
+
+    policy.invalidate(0, 3);
+    assert_eq!(policy.victim(0), 3);
+}
+
+#[test]
+fn srrip() {
+    let mut policy = SrripFactory { rrpv_bits: 2 }.generate(1, 4);
+    fill_all(&mut *policy, 1, 4);
+
+    // Every block was inserted with a long interval, the first one is aged
+    assert_eq!(policy.victim(0), 0);
+    policy.touch(0, 0);
+    policy.touch(0, 1);
+    assert_eq!(policy.victim(0), 2);
+
+    policy.touch(0, 2);
+    policy.touch(0, 3);
+    policy.invalidate(0, 1);
+    assert_eq!(policy.victim(0), 1);
+}
+
+#[test]
+fn brrip_inserts_distant_blocks() {
+    let factory = BrripFactory {
+        rrpv_bits: 2,
+        insertion_probability: 0.0,
+        seed: 0,
+    };
+    let mut policy = factory.generate(1, 4);
+    fill_all(&mut *policy, 1, 4);
+    policy.touch(0, 0);
+
+    // Without aging, the recently inserted blocks are already victims
+    assert_eq!(policy.victim(0), 1);
+    policy.fill(0, 1, 1);
+    assert_eq!(policy.victim(0), 1);
+}
+
+#[test]
+fn rrip_settings_out_of_range_are_rejected() {
+    let parse = |flags: &[&str]| {
+        let params: Vec<String> = flags.iter().map(|flag| flag.to_string()).collect();
+        LevelConfig::parse(&params, "rp=drrip")
+    };
+
+    assert!(parse(&["-rrpv-bits", "8"]).is_ok());
+    assert!(parse(&["-rrpv-bits", "0"]).is_err());
+    assert!(parse(&["-rrpv-bits", "9"]).is_err());
+    assert!(parse(&["-brrip-prob", "1"]).is_ok());
+    assert!(parse(&["-brrip-prob", "-0.5"]).is_err());
+    assert!(parse(&["-brrip-prob", "1.5"]).is_err());
+}
+
+#[test]
+fn set_dueling() {
+    let mut dueling = SetDueling::new(1024, ["A", "B"]);
+    assert_eq!(dueling.role(0), SetRole::LeaderA);
+    assert_eq!(dueling.role(16), SetRole::LeaderB);
+    assert_eq!(dueling.role(1), SetRole::Follower);
+    assert!(!dueling.uses_b(1));
+
+    // Misses in the leaders of A make the followers use B
+    for _ in 0..4 {
+        dueling.access();
+        dueling.miss(0);
+    }
+    assert!(dueling.uses_b(1));
+    assert!(!dueling.uses_b(32));
+    assert_eq!(dueling.timeline, vec![(0, "A"), (1, "B")]);
+}
+
+#[test]
+fn set_dueling_starts_agreeing_with_its_counter() {
+    let mut dueling = SetDueling::new(1024, ["A", "B"]);
+
+    // A miss in a leader of B keeps the followers on A
+    dueling.access();
+    dueling.miss(16);
+    assert!(!dueling.uses_b(1));
+    assert_eq!(dueling.timeline, vec![(0, "A")]);
+}
+
+#[test]
+fn set_dueling_needs_followers() {
+    let mut dueling = SetDueling::new(1, ["A", "B"]);
+    dueling.access();
+    dueling.miss(0);
+    assert!(!dueling.uses_b(0));
+    assert!(dueling.timeline.is_empty());
+
+    let mut log = Logger::default();
+    dueling.report(&mut log);
+    assert!(log.dueling_winners.is_empty());
+    assert_eq!(log.dueling_fallback, Some("A"));
+}
+
+#[test]
+fn opt() {
+    let factory = OptFactory::default();
+    let mut policy = factory.generate(1, 2);
+
+    // Blocks 1, 2, 3, 1, 3
+    let next_uses = factory.lookahead().unwrap();
+    next_uses.build([(0, 1), (0, 2), (0, 3), (0, 1), (0, 3)]);
+    assert_eq!(next_uses.current(), 3);
+
+    policy.fill(0, 0, 0);
+    next_uses.advance();
+    policy.fill(0, 1, 1);
+    next_uses.advance();
+
+    // Block 2 is never used again
+    assert_eq!(policy.victim(0), 1);
+    policy.fill(0, 1, 1);
+    next_uses.advance();
+    assert_eq!(next_uses.current(), NextUses::NEVER);
+    policy.touch(0, 0);
+
+    // Now block 3 is used sooner than block 1
+    assert_eq!(policy.victim(0), 0);
+}
+
+#[test]
+fn lfu_with_aging() {
+    let mut policy = LfuFactory { aging_period: 4 }.generate(1, 2);
+    fill_all(&mut *policy, 1, 2);
+
+    policy.touch(0, 0);
+    assert_eq!(policy.victim(0), 1);
+
+    // The fourth access halves the counters to 1 and 0
+    policy.touch(0, 1);
+    policy.touch(0, 1);
+    assert_eq!(policy.victim(0), 0);
+}
+
+#[test]
+fn lfru() {
+    let mut policy = LfruFactory.generate(1, 4);
+    fill_all(&mut *policy, 1, 4);
+
+    // Way 0 is the least frequently used unprivileged block
+    policy.touch(0, 1);
+    policy.touch(0, 2);
+    policy.touch(0, 3);
+    assert_eq!(policy.victim(0), 0);
+
+    // Way 2 is demoted when 0 becomes privileged. Ways 1 and 2 are used as
+    // often, so the least recently used one goes first
+    policy.fill(0, 0, 0);
+    policy.touch(0, 0);
+    assert_eq!(policy.victim(0), 1);
+}
+
+#[test]
+fn arc() {
+    let mut policy = ArcFactory.generate(1, 2);
+    let (a, b, c) = (10, 20, 30);
+
+    policy.miss(0, a);
+    policy.fill(0, 0, a);
+    policy.miss(0, b);
+    policy.fill(0, 1, b);
+    policy.touch(0, 1);
+
+    // `a` was only seen once, so it leaves and is remembered in B1
+    policy.miss(0, c);
+    assert_eq!(policy.victim(0), 0);
+    policy.fill(0, 0, c);
+
+    // Missing `a` again grows the recency side, so `b` leaves instead of `c`
+    policy.miss(0, a);
+    assert_eq!(policy.victim(0), 1);
+    policy.fill(0, 1, a);
+
+    let mut log = Logger::default();
+    policy.report(&mut log);
+    assert_eq!(log.ghost_hits, Some((1, 0)));
+}
+
+#[test]
+fn arc_only_changes_when_a_block_is_placed() {
+    let mut policy = ArcFactory.generate(1, 2);
+    let (a, b, c) = (10, 20, 30);
+
+    // Blocks placed without a miss, like prefetches, are tracked too
+    policy.fill(0, 0, a);
+    policy.fill(0, 1, b);
+    policy.touch(0, 1);
+
+    // A victim that is not replaced stays resident
+    policy.miss(0, c);
+    assert_eq!(policy.victim(0), 0);
+    assert_eq!(policy.victim(0), 0);
+    policy.touch(0, 0);
+
+    policy.miss(0, c);
+    assert_eq!(policy.victim(0), 1);
+    policy.fill(0, 1, c);
+
+    // `b` is remembered in B2 even if it comes back without a miss
+    policy.fill(0, 1, b);
+    let mut log = Logger::default();
+    policy.report(&mut log);
+    assert_eq!(log.ghost_hits, Some((0, 1)));
+}
+
+#[test]
+fn arc_ignores_write_misses_without_allocation() {
+    let reads = [0x40, 0x20, 0x10, 0x30, 0x30, 0x30];
+    let map_factories: [&dyn MapStrategyFactory; 2] =
+        [&FullyAssociativeFactory, &SetAssociativeFactory { sets: 1 }];
+
+    for map_factory in map_factories {
+        // Runs the reads, writing to `writes` after the third and the fifth
+        let run = |writes: [u64; 2]| {
+            let log = Rc::new(RefCell::new(Logger::default()));
+            let mut cache = Cache::new(
+                1,
+                2,
+                map_factory,
+                &ArcFactory,
+                WritePolicy::WriteThrough,
+                WriteMissPolicy::NoWriteAllocate,
+                Rc::clone(&log),
+            );
+            for (i, address) in reads.into_iter().enumerate() {
+                cache.access(Read(Data), address);
+                match i {
+                    2 => cache.access(Write, writes[0]),
+                    4 => cache.access(Write, writes[1]),
+                    _ => {}
+                }
+            }
+            cache.report();
+
+            let mut blocks: Vec<_> = cache.stored_blocks().collect();
+            blocks.sort();
+            let log = log.borrow();
+            (blocks, log.data_misses, log.ghost_hits)
+        };
+
+        // Writing to a stored block and missing without allocation must leave
+        // the same blocks, as the policy only hears about the hit
+        let (blocks, misses, ghost_hits) = run([0x10, 0x30]);
+        assert_eq!(blocks, vec![0x10, 0x30]);
+        assert_eq!(run([0x110, 0x140]), (blocks, misses + 2, ghost_hits));
+    }
+}
+
+#[test]
+fn lip_inserts_at_lru() {
+    let mut policy = LipFactory.generate(1, 4);
+    fill_all(&mut *policy, 1, 4);
+
+    // Every block was inserted in the LRU position, the last one is there
+    assert_eq!(policy.victim(0), 3);
+    policy.touch(0, 3);
+    assert_eq!(policy.victim(0), 2);
+    policy.fill(0, 2, 2);
+    assert_eq!(policy.victim(0), 2);
+}
+
+#[test]
+fn bip_epsilon_out_of_range_is_rejected() {
+    let parse = |epsilon: &str| {
+        let params = vec!["-bip-epsilon".to_owned(), epsilon.to_owned()];
+        LevelConfig::parse(&params, "rp=dip")
+    };
+
+    assert!(parse("0").is_ok());
+    assert!(parse("-0.1").is_err());
+    assert!(parse("1.1").is_err());
+}
+
+#[test]
+fn bip_sometimes_inserts_at_mru() {
+    let factory = BipFactory {
+        epsilon: 1.0,
+        seed: 0,
+    };
+    let mut policy = factory.generate(1, 4);
+    fill_all(&mut *policy, 1, 4);
+
+    // With epsilon 1 it behaves like LRU
+    assert_eq!(policy.victim(0), 0);
+}
@@ -0,0 +1,35 @@
+use cache_simulator::analysis::reuse::ReuseHistograms;
+use cache_simulator::cache::{
+    AccessType::{Read, Write},
+    ValueType::{Data, Instruction},
+};
+
+#[test]
+fn reuses_are_split_by_access_type() {
+    let mut histograms = ReuseHistograms::new(1);
+
+    histograms.access(Read(Instruction), 0x00);
+    histograms.access(Read(Data), 0x04);
+    histograms.access(Read(Data), 0x04);
+    histograms.access(Write, 0x04);
+    histograms.access(Read(Data), 0x08);
+    histograms.access(Read(Data), 0x08);
+    // Blocks a b b b c c a: distance 2 after 5 accesses
+    histograms.access(Read(Instruction), 0x00);
+
+    let instruction_reads = &histograms.instruction_reads;
+    assert_eq!(instruction_reads.cold, 1);
+    assert!(instruction_reads
+        .distances
+        .buckets()
+        .eq([(0..=0, 0), (1..=1, 0), (2..=3, 1)]));
+    assert!(instruction_reads
+        .times
+        .buckets()
+        .eq([(0..=0, 0), (1..=1, 0), (2..=3, 0), (4..=7, 1)]));
+
+    assert_eq!(histograms.data_reads.cold, 2);
+    assert!(histograms.data_reads.distances.buckets().eq([(0..=0, 2)]));
+    assert_eq!(histograms.writes.cold, 0);
+    assert!(histograms.writes.times.buckets().eq([(0..=0, 1)]));
+}
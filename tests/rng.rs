@@ -0,0 +1,7 @@
+use cache_simulator::rng::Rng;
+
+#[test]
+fn every_seed_gives_numbers() {
+    let mut rng = Rng::new(0x9E37_79B9_7F4A_7C15);
+    assert!((0..4).any(|_| rng.next_u64() != 0));
+}
@@ -0,0 +1,36 @@
+use cache_simulator::analysis::stack_distance::StackDistance;
+
+#[test]
+fn distances() {
+    let mut analysis = StackDistance::new(1);
+
+    // Blocks a b c a a c b
+    for address in [0x00, 0x04, 0x08, 0x00, 0x00, 0x08, 0x04] {
+        analysis.access(address);
+    }
+
+    assert_eq!(analysis.cold_accesses(), 3);
+    assert_eq!(analysis.distances(), &[1, 1, 2]);
+    assert_eq!(analysis.miss_ratio(1), 6.0 / 7.0);
+    assert_eq!(analysis.miss_ratio(2), 5.0 / 7.0);
+    assert_eq!(analysis.miss_ratio(3), 3.0 / 7.0);
+}
+
+#[test]
+fn long_traces() {
+    let mut analysis = StackDistance::new(2);
+
+    // A loop over 100 blocks, with two words per block
+    for _ in 0..50 {
+        for address in (0..800).step_by(4) {
+            analysis.access(address);
+        }
+    }
+
+    assert_eq!(analysis.accesses(), 10_000);
+    assert_eq!(analysis.cold_accesses(), 100);
+    assert_eq!(analysis.distances()[0], 5_000);
+    assert_eq!(analysis.distances()[99], 4_900);
+    assert_eq!(analysis.miss_ratio(99), 0.5);
+    assert_eq!(analysis.miss_ratio(100), 0.01);
+}
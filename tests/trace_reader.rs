@@ -0,0 +1,138 @@
+use cache_simulator::{
+    cache::{
+        AccessType::{Read, Write},
+        ValueType::{Data, Instruction},
+    },
+    trace_reader::{self, RawRecord, TraceFormat, TraceReader, TraceRecord},
+};
+use std::io::Cursor;
+use std::path::Path;
+
+#[test]
+fn traces_without_header_belong_to_a_single_thread() {
+    let trace = "2 40bc74\n\n0 10 data read miss (compulsory)\n";
+    let mut reader = TraceReader::new(Cursor::new(trace));
+
+    assert_eq!(reader.threads().unwrap(), None);
+    let (thread, access_type, address) = reader.next_access().unwrap().unwrap();
+    assert_eq!((thread, address), (0, 0x40bc74));
+    assert!(matches!(access_type, Read(Instruction)));
+
+    // Empty lines are skipped, and the text after the address ignored
+    let (thread, access_type, address) = reader.next_access().unwrap().unwrap();
+    assert_eq!((thread, address), (0, 0x10));
+    assert!(matches!(access_type, Read(Data)));
+    assert!(reader.next_access().is_none());
+}
+
+#[test]
+fn the_header_declares_the_threads_of_every_line() {
+    let trace = "#threads 2\n1 1 20\n0 0 24\n";
+    let mut reader = TraceReader::new(Cursor::new(trace));
+
+    assert_eq!(reader.threads().unwrap(), Some(2));
+    let (thread, access_type, address) = reader.next_access().unwrap().unwrap();
+    assert_eq!((thread, address), (1, 0x20));
+    assert!(matches!(access_type, Write));
+
+    // Reading without threads skips the column
+    let (access_type, address) = reader.next().unwrap().unwrap();
+    assert!(matches!(access_type, Read(Data)));
+    assert_eq!(address, 0x24);
+
+    let mut reader = TraceReader::new(Cursor::new("#threads 2\n2 0 24\n"));
+    assert!(reader.next_access().unwrap().is_err());
+}
+
+#[test]
+fn accesses_are_split_in_the_blocks_they_touch() {
+    let trace = "0 3c 8\n1 40 64 data write\n2 7e\n0 0 3\n";
+    let mut reader = TraceReader::new(Cursor::new(trace));
+    reader.set_block_size(4);
+
+    // Blocks of 16 bytes, so the first access straddles two of them
+    let addresses: Vec<_> = reader.by_ref().take(7).map(|a| a.unwrap().1).collect();
+    assert_eq!(addresses, [0x3c, 0x40, 0x40, 0x50, 0x60, 0x70, 0x7e]);
+
+    // Accesses of other sizes are rejected
+    assert!(reader.next().unwrap().is_err());
+}
+
+#[test]
+fn raw_records_can_be_split_for_any_block_size() {
+    let mut reader = TraceReader::new(Cursor::new("0 44 8\n"));
+    let Some(Ok(RawRecord::Access(_, access))) = reader.next_raw_record() else {
+        panic!("The access was not read");
+    };
+
+    // The same access touches one block of 16 bytes or two of 4 bytes
+    assert_eq!(reader.block_addresses(access, Some(4)).unwrap(), [0x44]);
+    assert_eq!(
+        reader.block_addresses(access, Some(1)).unwrap(),
+        [0x44, 0x48]
+    );
+    assert_eq!(reader.block_addresses(access, None).unwrap(), [0x44]);
+}
+
+#[test]
+fn addresses_keep_their_word_with_other_word_sizes() {
+    let trace = "0 3c 8\n0 41\n";
+    let mut reader = TraceReader::new(Cursor::new(trace));
+    reader.set_block_size(4);
+    reader.set_word_size(8);
+
+    // Words of 8 bytes are seen as words of 4 bytes by the caches
+    let addresses: Vec<_> = reader.map(|a| a.unwrap().1).collect();
+    assert_eq!(addresses, [0x1e, 0x20, 0x20]);
+    assert_eq!(trace_reader::trace_address(0x20, 8), 0x40);
+    assert_eq!(trace_reader::cache_address_width(32, 8), 31);
+}
+
+#[test]
+fn din_traces_have_escape_and_flush_records() {
+    let trace = "2 40bc74\n3 0\n4 0\n1 10 ignored\n";
+    let mut reader = TraceReader::new(Cursor::new(trace));
+    reader.set_format(TraceFormat::Din);
+
+    assert_eq!(reader.threads().unwrap(), None);
+    let record = reader.next_record().unwrap().unwrap();
+    assert!(matches!(
+        record,
+        TraceRecord::Access((0, Read(Instruction), 0x40bc74))
+    ));
+
+    // Escapes are skipped, while flushes are only skipped by the accesses
+    assert!(matches!(
+        reader.next_record().unwrap().unwrap(),
+        TraceRecord::Flush
+    ));
+    let (access_type, address) = reader.next().unwrap().unwrap();
+    assert!(matches!(access_type, Write));
+    assert_eq!(address, 0x10);
+
+    assert_eq!(TraceFormat::detect(Path::new("cc1.din")), TraceFormat::Din);
+    assert_eq!(
+        TraceFormat::detect(Path::new("spice.trace")),
+        TraceFormat::Text
+    );
+}
+
+#[test]
+fn lackey_modifies_read_and_then_write() {
+    let trace =
+        "==42== Lackey, an example Valgrind tool\nI  0400d7d4,3\n M 0421d7fc,8\n S 7ff0,4\n";
+    let mut reader = TraceReader::new(Cursor::new(trace));
+    reader.set_format(TraceFormat::Lackey);
+    reader.set_block_size(4);
+
+    let accesses: Vec<_> = reader.map(|a| a.unwrap()).collect();
+    assert_eq!(accesses.len(), 6);
+    assert!(matches!(accesses[0], (Read(Instruction), 0x400d7d4)));
+
+    // The modify straddles two blocks of 16 bytes, for the read and the write
+    assert!(matches!(accesses[1], (Read(Data), 0x421d7fc)));
+    assert!(matches!(accesses[2], (Read(Data), 0x421d800)));
+    assert!(matches!(accesses[3], (Write, 0x421d7fc)));
+    assert!(matches!(accesses[4], (Write, 0x421d800)));
+    assert!(matches!(accesses[5], (Write, 0x7ff0)));
+}
@@ -0,0 +1,63 @@
+use cache_simulator::{
+    cache::{
+        AccessType::{Read, Write},
+        Cache,
+        ValueType::Data,
+        WriteMissPolicy, WritePolicy,
+    },
+    logger::Logger,
+    map_strategies::direct_map::DirectMapFactory,
+    replacement_policies::lru::LruFactory,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Builds a direct mapped write-back cache of one word blocks with a victim
+/// cache of `entries` blocks.
+fn cache(cache_size: usize, entries: usize) -> (Cache, Rc<RefCell<Logger>>) {
+    let log = Rc::new(RefCell::new(Logger::default()));
+    let mut cache = Cache::new(
+        1,
+        cache_size,
+        &DirectMapFactory,
+        &LruFactory,
+        WritePolicy::WriteBack,
+        WriteMissPolicy::WriteAllocate,
+        Rc::clone(&log),
+    );
+    cache.set_victim_cache(entries);
+    (cache, log)
+}
+
+#[test]
+fn conflicting_blocks_are_swapped() {
+    let (mut cache, log) = cache(2, 1);
+
+    // 0x00 and 0x08 go to the same block
+    for address in [0x00, 0x08, 0x00, 0x08, 0x00] {
+        cache.access(Read(Data), address);
+    }
+
+    let log = log.borrow();
+    assert_eq!(log.data_misses, 5);
+    assert_eq!(log.victim_hits, Some(3));
+    assert_eq!(log.memory_reads, 2);
+}
+
+#[test]
+fn dirty_blocks_are_written_back_when_they_leave_the_victim_cache() {
+    let (mut cache, log) = cache(1, 1);
+
+    cache.access(Write, 0x00);
+    cache.access(Read(Data), 0x04);
+    // The dirty block comes back from the victim cache still dirty
+    cache.access(Read(Data), 0x00);
+    assert_eq!(log.borrow().memory_writes, 0);
+
+    cache.access(Read(Data), 0x08);
+    cache.access(Read(Data), 0x0c);
+
+    let log = log.borrow();
+    assert_eq!(log.victim_hits, Some(1));
+    assert_eq!(log.memory_writes, 1);
+}